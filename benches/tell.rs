@@ -0,0 +1,126 @@
+//! Allocations and time per message, for events sent with `send` and with `tell`.
+//!
+//! Run with `cargo bench --bench tell`.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+use std::alloc::{GlobalAlloc, System, Layout};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+use bottle::{Output, Remote, Handler, EventQueue};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Allocator counting the allocations.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+	unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		System.alloc(layout)
+	}
+
+	unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+		System.dealloc(ptr, layout)
+	}
+
+	unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+		ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+		System.realloc(ptr, layout, new_size)
+	}
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const MESSAGES: usize = 100_000;
+
+struct Counter {
+	count: usize
+}
+
+/// Event handled synchronously.
+struct Now;
+
+impl bottle::Event for Now {
+	type Response = usize;
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Now> for Counter {
+	fn handle<'a>(mut self: Receiver<'a, Self>, _event: Now) -> Output<'a, usize> {
+		self.count += 1;
+		Output::Now(self.count)
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Now> for Counter {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Now) -> Output<'a, usize> {
+		self.count += 1;
+		Output::Now(self.count)
+	}
+}
+
+/// Event handled asynchronously.
+struct Later;
+
+impl bottle::Event for Later {
+	type Response = usize;
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Later> for Counter {
+	fn handle<'a>(mut self: Receiver<'a, Self>, _event: Later) -> Output<'a, usize> {
+		async move {
+			self.count += 1;
+			self.count
+		}.into()
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Later> for Counter {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Later) -> Output<'a, usize> {
+		async move {
+			self.count += 1;
+			self.count
+		}.into()
+	}
+}
+
+/// Send `MESSAGES` events with the given function, then process them in the current thread.
+///
+/// Returns the number of allocations per message, and the total time.
+fn measure(send: impl Fn(&Remote<Counter>)) -> (f64, Duration) {
+	let queue = EventQueue::new();
+	let counter = Remote::new(queue.reference(), Counter { count: 0 });
+	let reference = queue.reference();
+
+	let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+	let start = Instant::now();
+
+	for _ in 0..MESSAGES {
+		send(&counter)
+	}
+
+	reference.close();
+	queue.run_blocking();
+
+	let time = start.elapsed();
+	let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+	(allocations as f64 / MESSAGES as f64, time)
+}
+
+fn report(name: &str, (allocations, time): (f64, Duration)) {
+	println!("{:<24} {:>8.2} allocations/message {:>10.1?}", name, allocations, time);
+}
+
+fn main() {
+	report("send (sync handler)", measure(|counter| counter.send(Now).detach()));
+	report("tell (sync handler)", measure(|counter| counter.tell(Now)));
+	report("send (async handler)", measure(|counter| counter.send(Later).detach()));
+	report("tell (async handler)", measure(|counter| counter.tell(Later)));
+}
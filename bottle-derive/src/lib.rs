@@ -0,0 +1,347 @@
+//! Derive macros for the `bottle` actor model framework.
+//!
+//! These macros are re-exported by `bottle` with the `derive` feature, and should be used from
+//! there.
+//! The generated code refers to the `bottle` crate as `::bottle`. If the crate is renamed, its
+//! path can be given with the `crate = path` option of each macro.
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::{quote, format_ident};
+use syn::{parse_macro_input, parse_quote, DeriveInput, Data, Fields, ItemImpl, ImplItem, FnArg, Meta, Path, PathArguments, GenericArgument, Type, Ident, Error};
+
+/// Parse the `crate = path` option of an attribute.
+fn crate_path(meta: &syn::meta::ParseNestedMeta, krate: &mut Path) -> syn::Result<()> {
+	*krate = meta.value()?.parse()?;
+	Ok(())
+}
+
+/// Implement `Event` for the given type.
+///
+/// The response type is given with `#[event(response = Type)]`, and is `()` by default.
+/// The priority is given with `#[event(priority = Level)]`, where `Level` is a `Priority`
+/// variant.
+#[proc_macro_derive(Event, attributes(event))]
+pub fn derive_event(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	match event(input) {
+		Ok(tokens) => tokens.into(),
+		Err(e) => e.to_compile_error().into()
+	}
+}
+
+fn event(input: DeriveInput) -> syn::Result<TokenStream2> {
+	let mut krate: Path = parse_quote!(::bottle);
+	let mut response: Type = parse_quote!(());
+	let mut priority: Option<Ident> = None;
+
+	for attr in &input.attrs {
+		if attr.path().is_ident("event") {
+			attr.parse_nested_meta(|meta| {
+				if meta.path.is_ident("response") {
+					response = meta.value()?.parse()?;
+					Ok(())
+				} else if meta.path.is_ident("priority") {
+					priority = Some(meta.value()?.parse()?);
+					Ok(())
+				} else if meta.path.is_ident("crate") {
+					crate_path(&meta, &mut krate)
+				} else {
+					Err(meta.error("expected `response`, `priority` or `crate`"))
+				}
+			})?;
+		}
+	}
+
+	let priority = priority.map(|priority| quote! {
+		const PRIORITY: #krate::Priority = #krate::Priority::#priority;
+	});
+
+	let ident = &input.ident;
+	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+	Ok(quote! {
+		impl #impl_generics #krate::Event for #ident #ty_generics #where_clause {
+			type Response = #response;
+			#priority
+		}
+	})
+}
+
+/// Implement `Handler` for each method of the given `impl` block marked with `#[handle]`.
+///
+/// Each handler method handles the event type of its last parameter, and returns the response
+/// of the event. `async` methods are handled with `Output::Later`, and the others with
+/// `Output::Now`:
+///
+/// ```ignore
+/// #[bottle::actor]
+/// impl Counter {
+///     #[handle]
+///     fn increment(&mut self, _event: Increment) -> usize {
+///         self.count += 1;
+///         self.count
+///     }
+///
+///     #[handle]
+///     async fn forward(&mut self, ctx: &mut Context<Self>, Forward(remote): Forward) {
+///         remote.send(Increment).await.unwrap();
+///     }
+/// }
+/// ```
+///
+/// A method marked with `#[handle(reentrant)]` is handled with `Output::Reentrant`: it must not
+/// be `async`, and returns a `'static` future instead, which cannot borrow the actor state.
+///
+/// Handler methods take `&mut self` or `&self`, optionally followed by the actor `Context`.
+/// With the `nightly` feature of `bottle`, they may take `self: Receiver<Self>` instead, but no
+/// `Context`, and the calling crate must enable the `arbitrary_self_types` feature.
+#[proc_macro_attribute]
+pub fn actor(args: TokenStream, input: TokenStream) -> TokenStream {
+	let mut krate: Path = parse_quote!(::bottle);
+	let parser = syn::meta::parser(|meta| {
+		if meta.path.is_ident("crate") {
+			crate_path(&meta, &mut krate)
+		} else {
+			Err(meta.error("expected `crate`"))
+		}
+	});
+	parse_macro_input!(args with parser);
+
+	let input = parse_macro_input!(input as ItemImpl);
+	match actor_impl(input, &krate) {
+		Ok(tokens) => tokens.into(),
+		Err(e) => e.to_compile_error().into()
+	}
+}
+
+fn actor_impl(mut input: ItemImpl, krate: &Path) -> syn::Result<TokenStream2> {
+	if let Some((_, path, _)) = &input.trait_ {
+		return Err(Error::new_spanned(path, "`#[actor]` expects an inherent `impl` block"))
+	}
+
+	let actor = &input.self_ty;
+	let (impl_generics, _, where_clause) = input.generics.split_for_impl();
+	let mut handlers = Vec::new();
+
+	for item in &mut input.items {
+		let method = match item {
+			ImplItem::Fn(method) => method,
+			_ => continue
+		};
+
+		let attr = match method.attrs.iter().position(|attr| attr.path().is_ident("handle")) {
+			Some(i) => method.attrs.remove(i),
+			None => continue
+		};
+
+		let mut reentrant = false;
+		if let Meta::List(_) = &attr.meta {
+			attr.parse_nested_meta(|meta| {
+				if meta.path.is_ident("reentrant") {
+					reentrant = true;
+					Ok(())
+				} else {
+					Err(meta.error("expected `reentrant`"))
+				}
+			})?;
+		}
+
+		let sig = &method.sig;
+		if !sig.generics.params.is_empty() {
+			return Err(Error::new_spanned(&sig.generics, "handler methods cannot be generic"))
+		}
+
+		let (kind, event) = match sig.inputs.iter().collect::<Vec<_>>().as_slice() {
+			[FnArg::Receiver(receiver), FnArg::Typed(event)] if matches!(&*receiver.ty, Type::Reference(_)) => (quote!(reference), &event.ty),
+			[FnArg::Receiver(receiver), FnArg::Typed(event)] if receiver.colon_token.is_some() => (quote!(receiver), &event.ty),
+			[FnArg::Receiver(receiver), FnArg::Typed(_), FnArg::Typed(event)] if matches!(&*receiver.ty, Type::Reference(_)) => (quote!(context), &event.ty),
+			_ => return Err(Error::new_spanned(sig, "handler methods take `&mut self` or `&self`, then the actor `Context` if any, then the event; or `self: Receiver<Self>` then the event"))
+		};
+
+		let execution = match (sig.asyncness, reentrant) {
+			(Some(asyncness), true) => return Err(Error::new_spanned(asyncness, "reentrant handler methods cannot be `async`: return a `'static` future instead")),
+			(Some(_), false) => quote!(later),
+			(None, true) => quote!(reentrant),
+			(None, false) => quote!(now)
+		};
+
+		let name = &sig.ident;
+		handlers.push(quote! {
+			#krate::__handler! {
+				#kind #execution [#impl_generics] (#actor) (#event) #name [#where_clause]
+			}
+		});
+	}
+
+	Ok(quote! {
+		#input
+		#(#handlers)*
+	})
+}
+
+/// Get the event type of a `Demux<E>` field type.
+fn demux_event(ty: &Type) -> Option<&Type> {
+	let path = match ty {
+		Type::Path(path) if path.qself.is_none() => &path.path,
+		_ => return None
+	};
+
+	let segment = path.segments.last()?;
+	if segment.ident != "Demux" {
+		return None
+	}
+
+	match &segment.arguments {
+		PathArguments::AngleBracketed(args) if args.args.len() == 1 => match &args.args[0] {
+			GenericArgument::Type(event) => Some(event),
+			_ => None
+		},
+		_ => None
+	}
+}
+
+/// Implement `Emitter<E>` for each `Demux<E>` field of the given struct.
+///
+/// Subscribers are added to and removed from the `Demux` field of their event type.
+/// Fields are discovered by their type name, `Demux`: a field can be ignored with
+/// `#[emitter(skip)]`.
+///
+/// A `<Name>Subscriptions` trait is also generated and implemented for `Remote<Name>`, with
+/// typed `subscribe_<field>` and `unsubscribe_<field>` methods for each field. They take any
+/// actor handling the event. The method names can be changed with `#[emitter(name = ident)]`
+/// on the field:
+///
+/// ```ignore
+/// #[derive(bottle::Emitter)]
+/// struct Button {
+///     #[emitter(name = clicks)]
+///     clicked: Demux<Clicked>
+/// }
+///
+/// button.subscribe_clicks(&listener).await;
+/// ```
+#[proc_macro_derive(Emitter, attributes(emitter))]
+pub fn derive_emitter(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	match emitter(input) {
+		Ok(tokens) => tokens.into(),
+		Err(e) => e.to_compile_error().into()
+	}
+}
+
+fn emitter(input: DeriveInput) -> syn::Result<TokenStream2> {
+	let mut krate: Path = parse_quote!(::bottle);
+	for attr in &input.attrs {
+		if attr.path().is_ident("emitter") {
+			attr.parse_nested_meta(|meta| {
+				if meta.path.is_ident("crate") {
+					crate_path(&meta, &mut krate)
+				} else {
+					Err(meta.error("expected `crate`"))
+				}
+			})?;
+		}
+	}
+
+	let fields = match &input.data {
+		Data::Struct(data) => match &data.fields {
+			Fields::Named(fields) => &fields.named,
+			_ => return Err(Error::new_spanned(&input.ident, "`Emitter` can only be derived for structs with named fields"))
+		},
+		_ => return Err(Error::new_spanned(&input.ident, "`Emitter` can only be derived for structs with named fields"))
+	};
+
+	let ident = &input.ident;
+	let vis = &input.vis;
+	let generics = &input.generics;
+	let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+	let actor = quote!(#ident #ty_generics);
+	let subscriptions = format_ident!("{}Subscriptions", ident);
+
+	let mut events: Vec<String> = Vec::new();
+	let mut impls = Vec::new();
+	let mut declarations = Vec::new();
+	let mut definitions = Vec::new();
+
+	for field in fields {
+		let mut skip = false;
+		let mut name = field.ident.clone().unwrap();
+		for attr in &field.attrs {
+			if attr.path().is_ident("emitter") {
+				attr.parse_nested_meta(|meta| {
+					if meta.path.is_ident("skip") {
+						skip = true;
+						Ok(())
+					} else if meta.path.is_ident("name") {
+						name = meta.value()?.parse()?;
+						Ok(())
+					} else {
+						Err(meta.error("expected `skip` or `name`"))
+					}
+				})?;
+			}
+		}
+
+		let event = match demux_event(&field.ty) {
+			Some(event) if !skip => event,
+			_ => continue
+		};
+
+		let event_name = quote!(#event).to_string();
+		if events.contains(&event_name) {
+			return Err(Error::new_spanned(&field.ty, "another `Demux` field has the same event type"))
+		}
+
+		events.push(event_name.clone());
+
+		let field = &field.ident;
+		impls.push(quote! {
+			impl #impl_generics #krate::Emitter<#event> for #actor #where_clause {
+				fn subscribe(&mut self, remote: #krate::Remote<dyn #krate::Handler<#event>>) -> bool {
+					self.#field.subscribe(&remote)
+				}
+
+				fn unsubscribe(&mut self, remote: #krate::Remote<dyn #krate::Handler<#event>>) -> bool {
+					self.#field.unsubscribe(&remote)
+				}
+			}
+		});
+
+		let subscribe = format_ident!("subscribe_{}", name);
+		let unsubscribe = format_ident!("unsubscribe_{}", name);
+		let subscribe_doc = format!("Subscribe the given actor to the `{}` events.", event_name);
+		let unsubscribe_doc = format!("Unsubscribe the given actor from the `{}` events.", event_name);
+		declarations.push(quote! {
+			#[doc = #subscribe_doc]
+			fn #subscribe<S: 'static + #krate::Handler<#event>>(&self, subscriber: &#krate::Remote<S>) -> #krate::Future<#actor, bool> where #actor: 'static, #event: 'static;
+
+			#[doc = #unsubscribe_doc]
+			fn #unsubscribe<S: 'static + #krate::Handler<#event>>(&self, subscriber: &#krate::Remote<S>) -> #krate::Future<#actor, bool> where #actor: 'static, #event: 'static;
+		});
+
+		definitions.push(quote! {
+			fn #subscribe<S: 'static + #krate::Handler<#event>>(&self, subscriber: &#krate::Remote<S>) -> #krate::Future<#actor, bool> where #actor: 'static, #event: 'static {
+				let subscriber = #krate::upcast!(subscriber.clone() => dyn #krate::Handler<#event>);
+				self.send(#krate::SubscriptionEvent::Subscribe(subscriber))
+			}
+
+			fn #unsubscribe<S: 'static + #krate::Handler<#event>>(&self, subscriber: &#krate::Remote<S>) -> #krate::Future<#actor, bool> where #actor: 'static, #event: 'static {
+				let subscriber = #krate::upcast!(subscriber.clone() => dyn #krate::Handler<#event>);
+				self.send(#krate::SubscriptionEvent::Unsubscribe(subscriber))
+			}
+		});
+	}
+
+	let doc = format!("Typed subscriptions to the events emitted by [`{}`].", ident);
+	Ok(quote! {
+		#(#impls)*
+
+		#[doc = #doc]
+		#vis trait #subscriptions #generics #where_clause {
+			#(#declarations)*
+		}
+
+		impl #impl_generics #subscriptions #ty_generics for #krate::Remote<#actor> #where_clause {
+			#(#definitions)*
+		}
+	})
+}
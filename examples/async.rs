@@ -1,5 +1,9 @@
-#![feature(arbitrary_self_types)]
-use bottle::{Output, Receiver, Remote, Handler, EventQueue};
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+use bottle::{Output, Remote, Handler, EventQueue};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
 
 pub struct Foo {
 	pongs: usize
@@ -14,6 +18,7 @@ impl bottle::Event for Event {
 	type Response = ();
 }
 
+#[cfg(feature = "nightly")]
 impl Handler<Event> for Foo {
 	fn handle<'a>(mut self: Receiver<'a, Self>, event: Event) -> Output<'a, ()> {
 		match event {
@@ -31,6 +36,24 @@ impl Handler<Event> for Foo {
 	}
 }
 
+#[cfg(not(feature = "nightly"))]
+impl Handler<Event> for Foo {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, event: Event) -> Output<'a, ()> {
+		match event {
+			Event::Ping(remote) => async move {
+				println!("ping");
+				remote.send(Event::Pong).await;
+				self.pongs += 1;
+				println!("done: {}", self.pongs)
+			}.into(),
+			Event::Pong => {
+				println!("pong");
+				Output::Now(())
+			}
+		}
+	}
+}
+
 #[async_std::main]
 async fn main() {
 	let queue = EventQueue::new();
@@ -42,8 +65,8 @@ async fn main() {
 		async_std::task::block_on(queue.process())
 	});
 
-	a.send(Event::Ping(b.clone()));
-	a.send(Event::Ping(b.clone()));
-	a.send(Event::Ping(b.clone()));
+	a.send(Event::Ping(b.clone())).detach();
+	a.send(Event::Ping(b.clone())).detach();
+	a.send(Event::Ping(b.clone())).detach();
 	a.send(Event::Ping(b)).await;
 }
@@ -0,0 +1,78 @@
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+use bottle::{Output, Remote, Handler, EventQueue};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+pub struct Foo {
+	pongs: usize
+}
+
+pub enum Event {
+	Ping(Remote<dyn Handler<Event>>),
+	Pong
+}
+
+impl bottle::Event for Event {
+	type Response = ();
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Event> for Foo {
+	fn handle<'a>(mut self: Receiver<'a, Self>, event: Event) -> Output<'a, ()> {
+		match event {
+			Event::Ping(remote) => async move {
+				println!("ping");
+				remote.send(Event::Pong).await;
+				self.pongs += 1;
+				println!("done: {}", self.pongs);
+
+				if self.pongs == 2 {
+					self.stop()
+				}
+			}.into(),
+			Event::Pong => {
+				println!("pong");
+				Output::Now(())
+			}
+		}
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Event> for Foo {
+	fn handle<'a>(&'a mut self, ctx: &'a mut Context<Self>, event: Event) -> Output<'a, ()> {
+		match event {
+			Event::Ping(remote) => async move {
+				println!("ping");
+				remote.send(Event::Pong).await;
+				self.pongs += 1;
+				println!("done: {}", self.pongs);
+
+				if self.pongs == 2 {
+					ctx.stop()
+				}
+			}.into(),
+			Event::Pong => {
+				println!("pong");
+				Output::Now(())
+			}
+		}
+	}
+}
+
+#[async_std::main]
+async fn main() {
+	let queue = EventQueue::new();
+
+	let a = Remote::new(queue.reference(), Foo { pongs: 0 });
+	let b = Remote::new(queue.reference(), Foo { pongs: 0 });
+	let b = bottle::upcast!(b => dyn Handler<Event>);
+
+	std::thread::spawn(move || queue.run_blocking());
+
+	a.send(Event::Ping(b.clone())).detach();
+	a.send(Event::Ping(b.clone())).await;
+	assert!(a.ask(Event::Ping(b)).await.is_err());
+}
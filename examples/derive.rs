@@ -0,0 +1,47 @@
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+use bottle::{Remote, EventQueue, Priority};
+
+pub struct Foo {
+	pongs: usize
+}
+
+#[derive(bottle::Event)]
+pub struct Ping(Remote<Foo>);
+
+#[derive(bottle::Event)]
+#[event(response = usize, priority = High)]
+pub struct Pong;
+
+#[bottle::actor]
+impl Foo {
+	#[handle]
+	async fn ping(&mut self, Ping(remote): Ping) {
+		println!("ping");
+		let pongs = remote.send(Pong).await;
+		self.pongs += 1;
+		println!("done: {} (remote pongs: {})", self.pongs, pongs);
+	}
+
+	#[handle]
+	fn pong(&self, _event: Pong) -> usize {
+		println!("pong");
+		self.pongs
+	}
+}
+
+#[async_std::main]
+async fn main() {
+	assert_eq!(<Pong as bottle::Event>::PRIORITY, Priority::High);
+
+	let queue = EventQueue::new();
+
+	let a = Remote::new(queue.reference(), Foo { pongs: 0 });
+	let b = Remote::new(queue.reference(), Foo { pongs: 0 });
+
+	std::thread::spawn(move || queue.run_blocking());
+
+	a.send(Ping(b.clone())).await;
+	a.send(Ping(b.clone())).await;
+	a.stop();
+	assert!(a.ask(Ping(b)).await.is_err());
+}
@@ -1,49 +1,32 @@
-#![feature(arbitrary_self_types)]
-
-#[macro_use]
-extern crate bottle;
-use bottle::{Output, Receiver, Remote, Handler, EventQueue, Demux};
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+use bottle::{Remote, EventQueue, Demux};
 
+#[derive(bottle::Emitter)]
 struct Foo {
+	#[emitter(name = event1)]
 	event1_demux: Demux<Event1>,
+
+	#[emitter(name = event2)]
 	event2_demux: Demux<Event2>
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, bottle::Event)]
 struct Event1;
 
-impl bottle::Event for Event1 {
-	type Response = ();
-}
-
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, bottle::Event)]
 struct Event2;
 
-impl bottle::Event for Event2 {
-	type Response = ();
-}
-
-emitter! {
-	Foo {
-		event1_demux: Event1,
-		event2_demux: Event2
-	}
-}
-
+#[derive(bottle::Event)]
 struct Emit;
 
-impl bottle::Event for Emit {
-	type Response = ();
-}
-
-impl Handler<Emit> for Foo {
-	fn handle(self: Receiver<Self>, _event: Emit) -> Output<()> {
+#[bottle::actor]
+impl Foo {
+	#[handle]
+	fn emit(&mut self, _event: Emit) {
 		println!("emit!");
 
 		self.event1_demux.send(Event1);
 		self.event2_demux.send(Event2);
-
-		Output::Now(())
 	}
 }
 
@@ -51,16 +34,17 @@ struct Bar {
 	// ...
 }
 
-impl Handler<Event1> for Bar {
-	fn handle(self: Receiver<Self>, _event: Event1) -> Output<()> {
+#[bottle::actor]
+impl Bar {
+	#[handle]
+	fn event1(&mut self, _event: Event1) {
 		println!("received!");
-		Output::Now(())
 	}
 }
 
 fn main() {
 	let queue = EventQueue::new();
-	let foo = Remote::new(queue.reference(), Foo {
+	let emitter = Remote::new(queue.reference(), Foo {
 		event1_demux: Demux::new(),
 		event2_demux: Demux::new()
 	});
@@ -68,9 +52,10 @@ fn main() {
 	let rec1 = Remote::new(queue.reference(), Bar {});
 	let rec2 = Remote::new(queue.reference(), Bar {});
 
-	foo.subscribe(rec1.clone());
-	foo.subscribe(rec2.clone());
-	foo.send(Emit);
+	emitter.subscribe_event1(&rec1).detach();
+	emitter.subscribe_event1(&rec2).detach();
+	emitter.send(Emit).detach();
 
-	async_std::task::block_on(queue.process());
+	queue.reference().close();
+	queue.run_blocking();
 }
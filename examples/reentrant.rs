@@ -0,0 +1,102 @@
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+use bottle::{Output, Remote, Handler, EventQueue};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Answers requests, after asking the client for its name.
+pub struct Server;
+
+/// Sends requests to the server, and answers its callbacks.
+pub struct Client {
+	name: &'static str
+}
+
+pub struct Request(Remote<Client>);
+
+impl bottle::Event for Request {
+	type Response = String;
+}
+
+pub struct Name;
+
+impl bottle::Event for Name {
+	type Response = &'static str;
+}
+
+pub struct Start(Remote<Server>);
+
+impl bottle::Event for Start {
+	type Response = String;
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Request> for Server {
+	fn handle<'a>(self: Receiver<'a, Self>, Request(client): Request) -> Output<'a, String> {
+		async move {
+			let name = client.send(Name).await;
+			format!("hello {}", name)
+		}.into()
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Name> for Client {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Name) -> Output<'a, &'static str> {
+		Output::Now(self.name)
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Start> for Client {
+	fn handle<'a>(self: Receiver<'a, Self>, Start(server): Start) -> Output<'a, String> {
+		// With `Output::Later`, the client would be busy until the server responds, and could not
+		// handle the `Name` callback: the actors would wait for each other forever.
+		let this: Remote<Client> = self.as_remote();
+		Output::reentrant(async move {
+			server.send(Request(this)).await
+		})
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Request> for Server {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Request(client): Request) -> Output<'a, String> {
+		async move {
+			let name = client.send(Name).await;
+			format!("hello {}", name)
+		}.into()
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Name> for Client {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Name) -> Output<'a, &'static str> {
+		Output::Now(self.name)
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Start> for Client {
+	fn handle<'a>(&'a mut self, ctx: &'a mut Context<Self>, Start(server): Start) -> Output<'a, String> {
+		// With `Output::Later`, the client would be busy until the server responds, and could not
+		// handle the `Name` callback: the actors would wait for each other forever.
+		let this: Remote<Client> = ctx.as_remote();
+		Output::reentrant(async move {
+			server.send(Request(this)).await
+		})
+	}
+}
+
+#[async_std::main]
+async fn main() {
+	let queue = EventQueue::new();
+
+	let server = Remote::new(queue.reference(), Server);
+	let client = Remote::new(queue.reference(), Client { name: "client" });
+
+	std::thread::spawn(move || queue.run_blocking());
+
+	println!("{}", client.send(Start(server)).await);
+}
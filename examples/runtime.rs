@@ -0,0 +1,53 @@
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+use bottle::{Output, Remote, Handler, Runtime, Placement};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+pub struct Counter {
+	count: usize
+}
+
+pub struct Increment;
+
+impl bottle::Event for Increment {
+	type Response = usize;
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Increment> for Counter {
+	fn handle<'a>(mut self: Receiver<'a, Self>, _event: Increment) -> Output<'a, usize> {
+		self.count += 1;
+		Output::Now(self.count)
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Increment> for Counter {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Increment) -> Output<'a, usize> {
+		self.count += 1;
+		Output::Now(self.count)
+	}
+}
+
+#[async_std::main]
+async fn main() {
+	let runtime = Runtime::new(4);
+
+	let counters: Vec<_> = (0..8).map(|_| {
+		Remote::new(runtime.queue(Placement::RoundRobin), Counter { count: 0 })
+	}).collect();
+
+	for counter in &counters {
+		counter.send(Increment).await;
+		assert_eq!(counter.send(Increment).await, 2);
+	}
+
+	let pinned = Remote::new(runtime.queue(Placement::Pinned(0)), Counter { count: 0 });
+	assert!(pinned.queue() == counters[0].queue());
+
+	runtime.shutdown();
+	let summaries = runtime.join();
+	println!("events per worker: {:?}", summaries.iter().map(|s| s.events).collect::<Vec<_>>());
+}
@@ -1,5 +1,9 @@
-#![feature(arbitrary_self_types)]
-use bottle::{Receiver, Remote, Handler, EventQueue};
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+use bottle::{Output, Remote, Handler, EventQueue};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
 
 struct Foo {
 	value: i32
@@ -19,28 +23,47 @@ impl bottle::Event for Event {
 	type Response = ();
 }
 
+#[cfg(feature = "nightly")]
 impl Handler<Reflect> for Foo {
-	fn handle(self: Receiver<Self>, _event: Reflect) -> Remote<dyn Handler<Event>> {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Reflect) -> Output<'a, Remote<dyn Handler<Event>>> {
 		let remote = self.as_remote();
-		remote as Remote<dyn Handler<Event>>
+		Output::Now(remote as Remote<dyn Handler<Event>>)
 	}
 }
 
+#[cfg(not(feature = "nightly"))]
+impl Handler<Reflect> for Foo {
+	fn handle<'a>(&'a mut self, ctx: &'a mut Context<Self>, _event: Reflect) -> Output<'a, Remote<dyn Handler<Event>>> {
+		let remote = ctx.as_remote();
+		Output::Now(bottle::upcast!(remote => dyn Handler<Event>))
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Event> for Foo {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Event) -> Output<'a, ()> {
+		println!("my value is {}", self.value);
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
 impl Handler<Event> for Foo {
-	fn handle(self: Receiver<Self>, _event: Event) {
-		println!("my value is {}", self.value)
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Event) -> Output<'a, ()> {
+		println!("my value is {}", self.value);
+		Output::Now(())
 	}
 }
 
 #[async_std::main]
 async fn main() {
 	let queue = EventQueue::new();
-	let foo = Remote::new(queue.reference(), Foo { value: 42 });
+	let actor = Remote::new(queue.reference(), Foo { value: 42 });
 
 	std::thread::spawn(move || {
 		async_std::task::block_on(queue.process())
 	});
 
-	let remote = foo.send(Reflect).await;
+	let remote = actor.send(Reflect).await;
 	remote.send(Event::Foo).await;
 }
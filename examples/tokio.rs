@@ -0,0 +1,51 @@
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+use bottle::{Output, Remote, Handler, EventQueue};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+pub struct Counter {
+	count: usize
+}
+
+pub struct Increment;
+
+impl bottle::Event for Increment {
+	type Response = usize;
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Increment> for Counter {
+	fn handle<'a>(mut self: Receiver<'a, Self>, _event: Increment) -> Output<'a, usize> {
+		self.count += 1;
+		Output::Now(self.count)
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Increment> for Counter {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Increment) -> Output<'a, usize> {
+		self.count += 1;
+		Output::Now(self.count)
+	}
+}
+
+fn main() {
+	let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+	let local = tokio::task::LocalSet::new();
+
+	local.block_on(&runtime, async {
+		let queue = EventQueue::new();
+		let counter = Remote::new(queue.reference(), Counter { count: 0 });
+		let reference = queue.reference();
+		let processor = queue.spawn_tokio();
+
+		counter.send(Increment).await;
+		assert_eq!(counter.send(Increment).await, 2);
+
+		reference.close();
+		let summary = processor.await.unwrap();
+		println!("events: {}", summary.events);
+	})
+}
@@ -0,0 +1,50 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread::{self, Thread};
+use std::task::{Wake, Context, Poll};
+use std::future::Future;
+
+/// Wakes the thread blocked on a future.
+///
+/// The wake-up is recorded in a flag, since the thread may also be parked and unparked by the
+/// polled future itself, for instance when a handler blocks on a channel: the unpark token alone
+/// could be consumed there.
+struct ThreadWaker {
+	thread: Thread,
+	woken: AtomicBool
+}
+
+impl Wake for ThreadWaker {
+	fn wake(self: Arc<Self>) {
+		self.wake_by_ref()
+	}
+
+	fn wake_by_ref(self: &Arc<Self>) {
+		self.woken.store(true, Ordering::Release);
+		self.thread.unpark()
+	}
+}
+
+/// Run a future to completion in the current thread.
+///
+/// The thread is parked until the future is woken up.
+pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+	let mut future = Box::pin(future);
+	let thread_waker = Arc::new(ThreadWaker {
+		thread: thread::current(),
+		woken: AtomicBool::new(false)
+	});
+	let waker = thread_waker.clone().into();
+	let mut ctx = Context::from_waker(&waker);
+
+	loop {
+		match future.as_mut().poll(&mut ctx) {
+			Poll::Ready(output) => return output,
+			Poll::Pending => {
+				while !thread_waker.woken.swap(false, Ordering::Acquire) {
+					thread::park()
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,113 @@
+use std::any::Any;
+use std::marker::PhantomData;
+use std::sync::{Arc, Weak};
+use crate::{Inner, Remote, Local, ThreadLocal, EventQueueRef, ActorId};
+
+/// Type erased actor.
+pub(crate) trait AnyInner {
+	fn id(&self) -> ActorId;
+
+	fn stop(&self);
+
+	fn is_cancelled(&self) -> bool;
+}
+
+impl<T: 'static> AnyInner for Inner<T> {
+	fn id(&self) -> ActorId {
+		Inner::id(self)
+	}
+
+	fn stop(&self) {
+		Inner::stop(self)
+	}
+
+	fn is_cancelled(&self) -> bool {
+		Inner::is_cancelled(self)
+	}
+}
+
+/// Context of an actor of type `T`.
+///
+/// Without the `nightly` feature, this is given to the handlers and lifecycle hooks along with the
+/// actor state, and replaces the [`Receiver`](crate::Receiver) `self` type to reach the actor
+/// pointers.
+// The layout does not depend on `T`, so that the context of an actor can be stored in its
+// `Inner`, whatever its type.
+#[repr(C)]
+pub struct Context<T: ?Sized> {
+	inner: Weak<dyn AnyInner>,
+	queue: EventQueueRef,
+	marker: PhantomData<*const T>
+}
+
+/// Context of an actor whose type is unknown.
+#[doc(hidden)]
+pub type AnyContext = Context<dyn Any>;
+
+impl<T: ?Sized> Context<T> {
+	pub(crate) fn new(inner: Weak<dyn AnyInner>, queue: EventQueueRef) -> Context<T> {
+		Context {
+			inner,
+			queue,
+			marker: PhantomData
+		}
+	}
+
+	/// View this context as the context of an actor of type `U`.
+	///
+	/// # Safety
+	/// The actor must be of type `U`, or `U` must be `dyn Any`.
+	pub(crate) unsafe fn cast<U: ?Sized>(&mut self) -> &mut Context<U> {
+		&mut *(self as *mut Context<T> as *mut Context<U>)
+	}
+
+	/// Identifier of the actor.
+	pub fn id(&self) -> ActorId {
+		// The actor is alive while its context is borrowed.
+		self.inner.upgrade().expect("actor is not reachable").id()
+	}
+
+	/// Stop the actor.
+	///
+	/// See [`Remote::stop`] for more details.
+	pub fn stop(&self) {
+		if let Some(inner) = self.inner.upgrade() {
+			inner.stop()
+		}
+	}
+
+	/// Checks if the event being handled has been cancelled.
+	///
+	/// An event is cancelled when its [`Future`](crate::Future) is dropped before the response.
+	/// Asynchronous handlers can check this between steps to avoid useless work.
+	pub fn is_cancelled(&self) -> bool {
+		self.inner.upgrade().is_some_and(|inner| inner.is_cancelled())
+	}
+}
+
+impl<T: 'static> Context<T> {
+	fn inner(&self) -> Arc<Inner<T>> {
+		// The actor is alive while its context is borrowed.
+		let inner = self.inner.upgrade().expect("actor is not reachable");
+		unsafe {
+			// This is safe because the context of an actor is only given with its own type.
+			Arc::from_raw(Arc::into_raw(inner) as *const Inner<T>)
+		}
+	}
+
+	/// Get a remote pointer to the actor.
+	pub fn as_remote(&self) -> Remote<T> {
+		Remote::from_inner(self.inner())
+	}
+
+	/// Get a local pointer to the actor.
+	pub fn as_local(&self) -> Local<T> {
+		Local::from_inner(self.inner())
+	}
+}
+
+unsafe impl<T: ?Sized> ThreadLocal for Context<T> {
+	fn queue(&self) -> &EventQueueRef {
+		&self.queue
+	}
+}
@@ -0,0 +1,205 @@
+use std::fmt;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicU8, AtomicU64, Ordering};
+use parking_lot::Mutex;
+use crate::ActorId;
+
+/// What to do when a request cycle between actors is detected.
+///
+/// An actor handling an event with an asynchronous handler is busy until the handler completes.
+/// If the handler awaits the response of another actor, which itself awaits the response of the
+/// first actor, directly or through other actors, none of them can ever respond.
+/// Such cycles are detected when the responses are awaited, unless the policy is `Ignore`.
+/// Handlers returning [`Output::Reentrant`](crate::Output::Reentrant) never take part in a
+/// cycle, since their actor is not busy.
+///
+/// The detection is opt-in: the default policy is `Ignore`, since the other policies take a
+/// process-wide lock whenever a busy actor awaits a response. See [`set_deadlock_policy`].
+///
+/// The detection is a debugging aid: it only runs in builds with `debug_assertions` enabled. In
+/// release builds, cycles are never detected, whatever the policy.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DeadlockPolicy {
+	/// Cycles are not detected.
+	Ignore,
+
+	/// The cycle is logged as an error with the [`log`] crate.
+	Log,
+
+	/// The handler closing the cycle panics, which fails its actor.
+	Panic,
+
+	/// The response closing the cycle resolves into [`SendError::Deadlock`](crate::SendError::Deadlock).
+	Fail
+}
+
+static POLICY: AtomicU8 = AtomicU8::new(0);
+
+/// Set the deadlock detection policy of the process.
+///
+/// Has no effect in release builds: see [`DeadlockPolicy`].
+pub fn set_deadlock_policy(policy: DeadlockPolicy) {
+	POLICY.store(policy as u8, Ordering::Relaxed)
+}
+
+/// Current deadlock detection policy of the process.
+pub fn deadlock_policy() -> DeadlockPolicy {
+	match POLICY.load(Ordering::Relaxed) {
+		0 => DeadlockPolicy::Ignore,
+		1 => DeadlockPolicy::Log,
+		2 => DeadlockPolicy::Panic,
+		_ => DeadlockPolicy::Fail
+	}
+}
+
+/// An actor in the wait graph.
+#[derive(Clone, Copy)]
+pub(crate) struct Node {
+	pub id: ActorId,
+
+	/// Type name of the actor state.
+	pub type_name: &'static str
+}
+
+impl fmt::Display for Node {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "{} `{}`", self.id, self.type_name)
+	}
+}
+
+/// An actor awaiting the response of another actor.
+struct Edge {
+	id: u64,
+	waiter: Node,
+	target: Node,
+
+	/// Type name of the awaited event.
+	event: &'static str
+}
+
+/// Edges of the wait graph, by waiter.
+fn graph() -> &'static Mutex<HashMap<ActorId, Vec<Edge>>> {
+	static GRAPH: OnceLock<Mutex<HashMap<ActorId, Vec<Edge>>>> = OnceLock::new();
+	GRAPH.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+thread_local! {
+	/// Busy actor whose handler is being polled in this thread.
+	static CURRENT: Cell<Option<Node>> = const { Cell::new(None) };
+}
+
+/// Set the busy actor whose handler is being polled in this thread, and return the previous one.
+pub(crate) fn enter(actor: Option<Node>) -> Option<Node> {
+	CURRENT.with(|current| current.replace(actor))
+}
+
+/// A cycle of actors awaiting each other.
+pub(crate) struct Cycle(Vec<(Node, Node, &'static str)>);
+
+impl fmt::Display for Cycle {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "deadlock detected:")?;
+		for (waiter, target, event) in &self.0 {
+			write!(f, "\n  actor {} awaits `{}` from actor {}", waiter, event, target)?;
+		}
+
+		Ok(())
+	}
+}
+
+/// An edge of the wait graph, removed when dropped.
+pub(crate) struct Wait {
+	waiter: ActorId,
+	id: u64
+}
+
+impl Drop for Wait {
+	fn drop(&mut self) {
+		let mut graph = graph().lock();
+		if let Some(edges) = graph.get_mut(&self.waiter) {
+			edges.retain(|edge| edge.id != self.id);
+			if edges.is_empty() {
+				graph.remove(&self.waiter);
+			}
+		}
+	}
+}
+
+/// Record that the current busy actor awaits the response of `target` to an event.
+///
+/// Returns `None` if no busy actor is being polled in this thread, if the policy is `Ignore`, or
+/// in release builds.
+/// The cycle closed by the new edge is reported according to the policy: with the `Fail`
+/// policy, the edge is not added and the cycle is returned.
+pub(crate) fn wait(target: Node, event: &'static str) -> Option<Result<Wait, Cycle>> {
+	let policy = deadlock_policy();
+	if !cfg!(debug_assertions) || policy == DeadlockPolicy::Ignore {
+		return None
+	}
+
+	let waiter = CURRENT.with(Cell::get)?;
+	let mut graph = graph().lock();
+	let cycle = find_path(&graph, target.id, waiter.id).map(|path| {
+		let mut cycle = vec![(waiter, target, event)];
+		cycle.extend(path.into_iter().map(|edge| (edge.waiter, edge.target, edge.event)));
+		Cycle(cycle)
+	});
+
+	if let Some(cycle) = cycle {
+		match policy {
+			DeadlockPolicy::Log => log::error!("{}", cycle),
+			DeadlockPolicy::Panic => {
+				std::mem::drop(graph);
+				panic!("{}", cycle)
+			},
+			_ => return Some(Err(cycle))
+		}
+	}
+
+	let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+	graph.entry(waiter.id).or_default().push(Edge {
+		id,
+		waiter,
+		target,
+		event
+	});
+
+	Some(Ok(Wait {
+		waiter: waiter.id,
+		id
+	}))
+}
+
+/// Find a path of edges from `from` to `to`.
+fn find_path(graph: &HashMap<ActorId, Vec<Edge>>, from: ActorId, to: ActorId) -> Option<Vec<&Edge>> {
+	let mut visited = Vec::new();
+	let mut path: Vec<&Edge> = Vec::new();
+	let mut stack: Vec<(ActorId, usize)> = vec![(from, 0)];
+
+	while let Some((node, next)) = stack.last_mut() {
+		if *node == to {
+			return Some(path)
+		}
+
+		let edge = graph.get(node).and_then(|edges| edges.get(*next));
+		*next += 1;
+		match edge {
+			Some(edge) if !visited.contains(&edge.target.id) => {
+				visited.push(edge.target.id);
+				path.push(edge);
+				stack.push((edge.target.id, 0))
+			},
+			Some(_) => (),
+			None => {
+				stack.pop();
+				path.pop();
+			}
+		}
+	}
+
+	None
+}
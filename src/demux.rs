@@ -1,7 +1,10 @@
 use std::collections::HashSet;
 use std::hash::{Hash, Hasher};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
 use parking_lot::Mutex;
-use crate::{Event, Remote, WeakRemote, Handler};
+use crate::{Event, Remote, WeakRemote, Handler, Ask, Timeout, SendError};
 
 #[derive(Clone)]
 struct Receiver<E: Event> {
@@ -22,7 +25,7 @@ impl<E: Event> Receiver<E> {
 
 impl<E: Event> PartialEq for Receiver<E> {
 	fn eq(&self, other: &Receiver<E>) -> bool {
-		self.ptr == other.ptr
+		self.ptr as *const () == other.ptr as *const ()
 	}
 }
 
@@ -30,7 +33,7 @@ impl<E: Event> Eq for Receiver<E> {}
 
 impl<E: Event> Hash for Receiver<E> {
 	fn hash<H: Hasher>(&self, h: &mut H) {
-		self.ptr.hash(h)
+		(self.ptr as *const ()).hash(h)
 	}
 }
 
@@ -55,19 +58,102 @@ impl<E: Event> Demux<E> {
 		subscribers.remove(&Receiver::new(actor))
 	}
 
-	pub fn send(&self, event: E) where E: 'static + Clone {
+	/// Get the subscribers that are still alive, and forget the others.
+	fn subscribers(&self) -> Vec<Remote<dyn Handler<E>>> {
 		let mut subscribers = self.subscribers.lock();
-		let mut to_remove = Vec::new();
-		for subscriber in subscribers.iter() {
-			if let Some(subscriber) = subscriber.remote.upgrade() {
-				subscriber.send(event.clone());
-			} else {
-				to_remove.push(subscriber.clone())
+		let mut alive = Vec::with_capacity(subscribers.len());
+		subscribers.retain(|subscriber| {
+			match subscriber.remote.upgrade() {
+				Some(subscriber) => {
+					alive.push(subscriber);
+					true
+				},
+				None => false
+			}
+		});
+
+		alive
+	}
+
+	pub fn send(&self, event: E) where E: 'static + Clone {
+		for subscriber in self.subscribers() {
+			subscriber.tell(event.clone());
+		}
+	}
+
+	/// Send the event to every subscriber, and wait for their responses.
+	///
+	/// The returned future resolves once every subscriber has responded, or failed to, with the
+	/// response of each subscriber. If a timeout is given, the subscribers that do not respond in
+	/// time fail with [`SendError::Timeout`]: it is measured with the clock of each subscriber's
+	/// queue, and elapses even if a subscriber's thread is stalled. See [`Ask::timeout`].
+	pub fn broadcast(&self, event: E, timeout: Option<Duration>) -> Broadcast<E> where E: 'static + Clone {
+		let replies = self.subscribers().into_iter().map(|subscriber| {
+			let future = subscriber.ask(event.clone());
+			let reply = match timeout {
+				Some(timeout) => Reply::Timeout(future.timeout(timeout)),
+				None => Reply::Pending(future)
+			};
+
+			(subscriber, reply)
+		}).collect();
+
+		Broadcast {
+			replies
+		}
+	}
+}
+
+impl<E: Event> Default for Demux<E> {
+	fn default() -> Demux<E> {
+		Demux::new()
+	}
+}
+
+enum Reply<E: 'static + Event> {
+	Pending(Ask<dyn Handler<E>, E::Response>),
+	Timeout(Timeout<dyn Handler<E>, E::Response>),
+	Done(Result<E::Response, SendError>)
+}
+
+/// Responses of the subscribers of a [`Demux`] to an event.
+///
+/// See [`Demux::broadcast`].
+/// Dropping this future before the responses cancels the events, as with [`Ask`].
+#[must_use = "the events are cancelled if the future is dropped"]
+pub struct Broadcast<E: 'static + Event> {
+	replies: Vec<(Remote<dyn Handler<E>>, Reply<E>)>
+}
+
+// The responses are never pinned.
+impl<E: 'static + Event> Unpin for Broadcast<E> {}
+
+impl<E: 'static + Event> futures::future::Future for Broadcast<E> {
+	type Output = Vec<(Remote<dyn Handler<E>>, Result<E::Response, SendError>)>;
+
+	fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Self::Output> {
+		let mut is_done = true;
+		for (_, reply) in &mut self.replies {
+			let poll = match reply {
+				Reply::Pending(future) => Pin::new(future).poll(ctx),
+				Reply::Timeout(future) => Pin::new(future).poll(ctx),
+				Reply::Done(_) => continue
+			};
+
+			match poll {
+				Poll::Ready(result) => *reply = Reply::Done(result),
+				Poll::Pending => is_done = false
 			}
 		}
 
-		for dead_subscriber in &to_remove {
-			subscribers.remove(dead_subscriber);
+		if is_done {
+			let replies = std::mem::take(&mut self.replies);
+			Poll::Ready(replies.into_iter().map(|(subscriber, reply)| match reply {
+				Reply::Done(result) => (subscriber, result),
+				_ => unreachable!()
+			}).collect())
+		} else {
+			Poll::Pending
 		}
 	}
 }
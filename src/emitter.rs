@@ -1,4 +1,8 @@
-use crate::{Event, Remote, Handler, Receiver, Output};
+use crate::{Event, Remote, Handler, Output};
+#[cfg(feature = "nightly")]
+use crate::Receiver;
+#[cfg(not(feature = "nightly"))]
+use crate::Context;
 
 pub trait Emitter<E: Event> {
 	fn subscribe(&mut self, remote: Remote<dyn Handler<E>>) -> bool;
@@ -15,35 +19,107 @@ impl<E: Event> Event for SubscriptionEvent<E> {
 	type Response = bool;
 }
 
+impl<E: Event> SubscriptionEvent<E> {
+	fn apply<T: ?Sized + Emitter<E>>(self, emitter: &mut T) -> bool {
+		match self {
+			SubscriptionEvent::Subscribe(remote) => emitter.subscribe(remote),
+			SubscriptionEvent::Unsubscribe(remote) => emitter.unsubscribe(remote)
+		}
+	}
+}
+
+#[cfg(feature = "nightly")]
 impl<E: Event, T: ?Sized + Emitter<E>> Handler<SubscriptionEvent<E>> for T {
 	fn handle<'a>(mut self: Receiver<'a, Self>, event: SubscriptionEvent<E>) -> Output<'a, bool> {
-		match event {
-			SubscriptionEvent::Subscribe(remote) => Output::Now(self.subscribe(remote)),
-			SubscriptionEvent::Unsubscribe(remote) => Output::Now(self.unsubscribe(remote))
-		}
+		Output::Now(event.apply(&mut *self))
 	}
 }
 
+#[cfg(not(feature = "nightly"))]
+impl<E: Event, T: Emitter<E>> Handler<SubscriptionEvent<E>> for T {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, event: SubscriptionEvent<E>) -> Output<'a, bool> {
+		Output::Now(event.apply(self))
+	}
+}
+
+/// Implement [`Emitter`] for the given `Demux` fields of an actor.
+///
+/// With the `derive` feature, prefer `#[derive(Emitter)]`, which discovers the `Demux` fields,
+/// supports generic actors and generates typed subscription methods.
 #[macro_export]
 macro_rules! emitter (
 	( $type:ty { $($field:ident : $event_type:ty),* } ) => {
 		$(
-			emitter_impl!($type, $field, $event_type);
+			$crate::emitter_impl!($type, $field, $event_type);
 		)*
 	}
 );
 
+#[doc(hidden)]
 #[macro_export]
 macro_rules! emitter_impl {
 	($type:ty, $field:ident, $event_type:ty) => {
-		impl ::bottle::Emitter<$event_type> for $type {
-			fn subscribe(&mut self, remote: ::bottle::Remote<dyn ::bottle::Handler<$event_type>>) -> bool {
+		impl $crate::Emitter<$event_type> for $type {
+			fn subscribe(&mut self, remote: $crate::Remote<dyn $crate::Handler<$event_type>>) -> bool {
 				self.$field.subscribe(&remote)
 			}
 
-			fn unsubscribe(&mut self, remote: ::bottle::Remote<dyn ::bottle::Handler<$event_type>>) -> bool {
+			fn unsubscribe(&mut self, remote: $crate::Remote<dyn $crate::Handler<$event_type>>) -> bool {
 				self.$field.unsubscribe(&remote)
 			}
 		}
 	}
 }
+
+/// Emitters rejected by `#[derive(Emitter)]`.
+///
+/// ```
+/// # use bottle::{Demux, Event};
+/// # #[derive(Clone, Event)] struct Clicked;
+/// #[derive(bottle::Emitter)]
+/// struct Button {
+///     clicked: Demux<Clicked>,
+///
+///     #[emitter(skip)]
+///     double_clicked: Demux<Clicked>
+/// }
+/// ```
+///
+/// Two fields cannot have the same event type:
+/// ```compile_fail
+/// # use bottle::{Demux, Event};
+/// # #[derive(Clone, Event)] struct Clicked;
+/// #[derive(bottle::Emitter)]
+/// struct Button {
+///     clicked: Demux<Clicked>,
+///     double_clicked: Demux<Clicked>
+/// }
+/// ```
+///
+/// Skipped fields have no subscription methods:
+/// ```compile_fail
+/// # use bottle::{Demux, Event, EventQueue, Remote};
+/// # #[derive(Clone, Event)] struct Clicked;
+/// #[derive(bottle::Emitter)]
+/// struct Button {
+///     #[emitter(skip)]
+///     clicked: Demux<Clicked>
+/// }
+///
+/// fn subscribe(button: Remote<Button>, listener: Remote<Button>) {
+///     button.subscribe_clicked(&listener);
+/// }
+/// ```
+///
+/// Unknown options are rejected:
+/// ```compile_fail
+/// # use bottle::{Demux, Event};
+/// # #[derive(Clone, Event)] struct Clicked;
+/// #[derive(bottle::Emitter)]
+/// struct Button {
+///     #[emitter(rename = clicks)]
+///     clicked: Demux<Clicked>
+/// }
+/// ```
+#[cfg(all(doctest, feature = "derive"))]
+mod derive_errors {}
@@ -0,0 +1,60 @@
+use std::fmt;
+
+/// Error returned when an event could not be handled by its receiver.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SendError {
+	/// The receiver is stopped, or has been stopped before handling the event.
+	ActorStopped,
+
+	/// The receiver's queue processor has terminated, or has been dropped, before handling the
+	/// event.
+	QueueClosed,
+
+	/// The handler or the constructor of the actor panicked, or a panic in the receiver's thread
+	/// prevented the event from being handled.
+	HandlerPanicked,
+
+	/// The receiver's mailbox is full: the event has been rejected, or dropped by the mailbox
+	/// overflow policy before being handled.
+	Full,
+
+	/// The receiver did not respond before its reply timeout, or the timeout of the response
+	/// future, elapsed.
+	///
+	/// See [`Remote::set_reply_timeout`](crate::Remote::set_reply_timeout) and
+	/// [`Ask::timeout`](crate::Ask::timeout).
+	Timeout,
+
+	/// Awaiting the response would close a cycle of actors awaiting each other.
+	///
+	/// Only returned with the [`DeadlockPolicy::Fail`](crate::DeadlockPolicy::Fail) policy.
+	Deadlock
+}
+
+impl fmt::Display for SendError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			SendError::ActorStopped => write!(f, "actor stopped"),
+			SendError::QueueClosed => write!(f, "event queue closed"),
+			SendError::HandlerPanicked => write!(f, "handler panicked"),
+			SendError::Full => write!(f, "mailbox full"),
+			SendError::Timeout => write!(f, "timeout elapsed"),
+			SendError::Deadlock => write!(f, "deadlock detected")
+		}
+	}
+}
+
+impl std::error::Error for SendError {}
+
+/// Error returned by [`Registry::register`](crate::Registry::register) when another actor is
+/// registered under the same name.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct AlreadyRegistered;
+
+impl fmt::Display for AlreadyRegistered {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "name already registered")
+	}
+}
+
+impl std::error::Error for AlreadyRegistered {}
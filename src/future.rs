@@ -1,8 +1,13 @@
-use std::sync::Arc;
+use std::sync::{Arc, Weak};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::pin::Pin;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Duration;
 use std::task::{Waker, Context, Poll};
 use parking_lot::Mutex;
-use crate::Remote;
+use crate::{Inner, Pending, SendError, deadlock};
+use crate::timer::Deadline;
+use crate::mailbox::{Acquire, Ticket, Reservation};
 
 // pub(crate) struct State<T> {
 // 	result: Option<T>,
@@ -22,17 +27,44 @@ use crate::Remote;
 // 	}
 // }
 
+/// Response of an actor to an event.
+///
+/// Resolves into the response of the actor.
+///
+/// # Panics
+/// Polling the future panics if the event could not be handled, for instance if the receiver is
+/// stopped or its handler panicked. Use [`Remote::ask`](crate::Remote::ask) to get a
+/// [`SendError`] instead.
+///
+/// # Cancellation
+/// See [`Ask`].
+#[must_use = "the event is cancelled if the future is dropped"]
 pub struct Future<R: ?Sized, T: 'static + Send> {
-	pub(crate) state: Arc<Mutex<State<R, T>>>
+	pub(crate) ask: Ask<R, T>
 }
 
 impl<R: ?Sized, T: 'static + Send> Future<R, T> {
-	pub(crate) fn new(state: Arc<Mutex<State<R, T>>>) -> Future<R, T> {
+	pub(crate) fn new(ask: Ask<R, T>) -> Future<R, T> {
 		Future {
-			state
+			ask
 		}
 	}
 
+	/// Drop the future without cancelling the event.
+	///
+	/// See [`Ask::detach`].
+	pub fn detach(self) {
+		self.ask.detach()
+	}
+
+	/// Wait for the response for at most the given duration.
+	///
+	/// Unlike this future, the returned future does not panic if the event could not be handled,
+	/// and resolves into a [`SendError`] instead: see [`Ask::timeout`].
+	pub fn timeout(self, duration: Duration) -> Timeout<R, T> {
+		self.ask.timeout(duration)
+	}
+
 	//
 	// pub fn from_future(future: Box<dyn Send + std::future::Future<Output = T>>) -> Future<T> {
 	// 	Future {
@@ -47,31 +79,212 @@ impl<R: ?Sized, T: 'static + Send> Future<R, T> {
 impl<R: ?Sized, T: 'static + Send> futures::future::Future for Future<R, T> {
 	type Output = T;
 
-	fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<T> {
-		let mut state = self.state.lock();
+	fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<T> {
+		match Pin::new(&mut self.ask).poll(ctx) {
+			Poll::Ready(Ok(response)) => Poll::Ready(response),
+			Poll::Ready(Err(error)) => panic!("event could not be handled: {}", error),
+			Poll::Pending => Poll::Pending
+		}
+	}
+}
+
+/// Response of an actor to an event, or the reason why it could not be handled.
+///
+/// Resolves into an error if the event could not be handled, so that waiting on a response never
+/// hangs because its receiver is gone.
+/// See [`Remote::ask`](crate::Remote::ask).
+///
+/// If the receiver's mailbox is full with the [`Overflow::Wait`](crate::Overflow::Wait) policy,
+/// the event is only delivered once this future is polled and the mailbox has some room.
+///
+/// If the receiver has a reply timeout, the future resolves into [`SendError::Timeout`] once it
+/// elapses: see [`Remote::set_reply_timeout`](crate::Remote::set_reply_timeout).
+///
+/// # Cancellation
+/// Dropping the future before the response cancels the event: it is skipped if it is not being
+/// handled yet, and otherwise its handler can observe the cancellation with
+/// [`Context::is_cancelled`](crate::Context::is_cancelled).
+/// Use [`Ask::detach`] to let the event be handled anyway.
+#[must_use = "the event is cancelled if the future is dropped"]
+pub struct Ask<R: ?Sized, T: 'static + Send> {
+	state: Arc<Mutex<State<R, T>>>,
+
+	/// Event waiting for some room in the mailboxes of its receiver.
+	blocked: Option<Box<dyn Pending>>,
+
+	/// Reply timeout of the receiver.
+	deadline: Option<Deadline>,
+
+	/// The event is not cancelled when the future is dropped.
+	is_detached: bool
+}
+
+impl<R: ?Sized, T: 'static + Send> Ask<R, T> {
+	pub(crate) fn new(state: Arc<Mutex<State<R, T>>>, blocked: Option<Box<dyn Pending>>) -> Ask<R, T> {
+		let deadline = {
+			let inner = &state.lock().inner;
+			inner.reply_timeout().map(|timeout| Deadline::new(inner.queue.clock(), timeout))
+		};
+
+		Ask {
+			state,
+			blocked,
+			deadline,
+			is_detached: false
+		}
+	}
+
+	/// Drop the future without cancelling the event.
+	///
+	/// The event is still handled, and the response is lost. If the event is waiting for some
+	/// room in a mailbox, it is dropped.
+	pub fn detach(mut self) {
+		self.is_detached = true
+	}
+
+	/// Wait for the response for at most the given duration.
+	///
+	/// The duration is measured with the clock of the receiver's queue, and elapses even if the
+	/// receiver's thread is busy or stalled.
+	/// If it elapses first, the returned future resolves into [`SendError::Timeout`], the event
+	/// is dropped if it is not being handled yet, and the response is lost.
+	pub fn timeout(self, duration: Duration) -> Timeout<R, T> {
+		let clock = self.state.lock().inner.queue.clock();
+		Timeout {
+			future: self,
+			deadline: Deadline::new(clock, duration)
+		}
+	}
+}
+
+impl<R: ?Sized, T: 'static + Send> futures::future::Future for Ask<R, T> {
+	type Output = Result<T, SendError>;
+
+	fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Result<T, SendError>> {
+		let this = &mut *self;
+		let elapsed = match &mut this.deadline {
+			Some(deadline) => Pin::new(deadline).poll(ctx).is_ready(),
+			None => false
+		};
+
+		if let Some(pending) = this.blocked.take() {
+			let inner = this.state.lock().inner.clone();
+			if inner.is_stopping() {
+				pending.discard(SendError::ActorStopped)
+			} else if elapsed {
+				pending.discard(SendError::Timeout)
+			} else {
+				this.blocked = inner.queue.deliver(pending, Some(ctx.waker()));
+				if this.blocked.is_some() {
+					return Poll::Pending
+				}
+			}
+		}
+
+		let mut state = this.state.lock();
 		state.waker = None;
 		let mut result = None;
 		std::mem::swap(&mut result, &mut state.result);
 		match result {
 			Some(result) => {
-				Poll::Ready(result)
+				Poll::Ready(Ok(result))
 			},
 			// Some(FutureResult::Later(mut future)) => {
 			// 	let poll = Pin::as_mut(&mut future).poll(ctx);
 			// 	state.result = Some(FutureResult::Later(future));
 			// 	poll
 			// },
-			None => {
-				state.waker = Some(ctx.waker().clone());
-				Poll::Pending
+			None => match state.error {
+				Some(error) => Poll::Ready(Err(error)),
+				None if elapsed => {
+					state.set_error(SendError::Timeout);
+					Poll::Ready(Err(SendError::Timeout))
+				},
+				None => {
+					if state.wait.is_none() {
+						let target = state.inner.node();
+						match deadlock::wait(target, state.event) {
+							Some(Ok(wait)) => state.wait = Some(wait),
+							Some(Err(_)) => {
+								state.set_error(SendError::Deadlock);
+								return Poll::Ready(Err(SendError::Deadlock))
+							},
+							None => ()
+						}
+					}
+
+					state.waker = Some(ctx.waker().clone());
+					Poll::Pending
+				}
 			}
 		}
 	}
 }
 
+/// Response of an actor to an event, with a timeout.
+///
+/// Resolves into [`SendError::Timeout`] if the response is not received in time, and into the
+/// result of the response future otherwise.
+/// See [`Ask::timeout`].
+pub struct Timeout<R: ?Sized, T: 'static + Send> {
+	future: Ask<R, T>,
+	deadline: Deadline
+}
+
+impl<R: ?Sized, T: 'static + Send> futures::future::Future for Timeout<R, T> {
+	type Output = Result<T, SendError>;
+
+	fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Result<T, SendError>> {
+		let this = &mut *self;
+		if let Poll::Ready(result) = Pin::new(&mut this.future).poll(ctx) {
+			return Poll::Ready(result)
+		}
+
+		match Pin::new(&mut this.deadline).poll(ctx) {
+			Poll::Ready(()) => {
+				State::fail(&this.future.state, SendError::Timeout);
+				Poll::Ready(Err(SendError::Timeout))
+			},
+			Poll::Pending => Poll::Pending
+		}
+	}
+}
+
+impl<R: ?Sized, T: 'static + Send> Drop for Ask<R, T> {
+	fn drop(&mut self) {
+		let mut state = self.state.lock();
+		state.wait = None;
+		if !self.is_detached {
+			state.cancel()
+		}
+	}
+}
+
 pub(crate) struct State<R: ?Sized, T: 'static + Send> {
-	remote: Remote<R>,
+	inner: Arc<Inner<R>>,
 	result: Option<T>,
+	error: Option<SendError>,
+	is_started: bool,
+	is_done: bool,
+
+	/// The local future does not borrow the actor.
+	is_reentrant: bool,
+
+	/// Type name of the event.
+	event: &'static str,
+
+	/// Edge of the deadlock detection graph, while the response is awaited by a busy actor.
+	wait: Option<deadlock::Wait>,
+
+	/// Set when the response is no longer awaited.
+	cancelled: Arc<AtomicBool>,
+
+	/// Room held by the event in the mailboxes.
+	reservation: Reservation,
+
+	/// Reference to this state given to the mailboxes.
+	ticket: Option<Weak<dyn Ticket>>,
+
 	waker: Option<Waker>,
 	local_waker: Option<Waker>,
 	local_future: Option<Pin<Box<dyn 'static + std::future::Future<Output = T>>>>
@@ -80,10 +293,19 @@ pub(crate) struct State<R: ?Sized, T: 'static + Send> {
 unsafe impl<R: ?Sized, T: 'static + Send> Send for State<R, T> {}
 
 impl<R: ?Sized, T: 'static + Send> State<R, T> {
-	pub fn new(remote: Remote<R>) -> Arc<Mutex<State<R, T>>> {
+	pub fn new(inner: Arc<Inner<R>>, event: &'static str) -> Arc<Mutex<State<R, T>>> {
 		Arc::new(Mutex::new(State {
-			remote,
+			inner,
 			result: None,
+			error: None,
+			is_started: false,
+			is_done: false,
+			is_reentrant: false,
+			event,
+			wait: None,
+			cancelled: Arc::new(AtomicBool::new(false)),
+			reservation: Reservation::default(),
+			ticket: None,
 			waker: None,
 			local_waker: None,
 			local_future: None
@@ -93,6 +315,8 @@ impl<R: ?Sized, T: 'static + Send> State<R, T> {
 	pub fn set(state: &Arc<Mutex<State<R, T>>>, value: T) {
 		let mut state = state.lock();
 		state.result = Some(value);
+		state.is_done = true;
+		state.wait = None;
 
 		let mut waker = None;
 		std::mem::swap(&mut waker, &mut state.waker);
@@ -107,12 +331,93 @@ impl<R: ?Sized, T: 'static + Send> State<R, T> {
 		}
 	}
 
+	/// Take some room for the event in the mailboxes of its receiver: the actor mailbox, then
+	/// the queue mailbox.
+	///
+	/// The room is released once the event is being handled, or cannot be handled.
+	pub fn reserve(state: &Arc<Mutex<State<R, T>>>, waker: Option<&Waker>) -> Acquire where R: 'static {
+		let this: Weak<Mutex<State<R, T>>> = Arc::downgrade(state);
+		let ticket: Weak<dyn Ticket> = this;
+
+		// The state is locked until the reservation is complete, so that the event cannot be
+		// dropped in the meantime.
+		let mut state = state.lock();
+		let state = &mut *state;
+		let result = state.reservation.acquire(&state.inner.mailbox, state.inner.queue.mailbox(), Some(&ticket), waker);
+		state.ticket = Some(ticket);
+		result
+	}
+
+	/// Mark the event as being handled, and release its room in the mailboxes.
+	///
+	/// Returns the cancellation flag of the event, or `None` if the event is already done because
+	/// it has been dropped or cancelled: it must not be handled.
+	pub fn start(state: &Arc<Mutex<State<R, T>>>) -> Option<Arc<AtomicBool>> {
+		let mut state = state.lock();
+		if state.is_done {
+			None
+		} else {
+			state.is_started = true;
+			state.release();
+			Some(state.cancelled.clone())
+		}
+	}
+
+	fn release(&mut self) {
+		self.reservation.release(&self.inner.mailbox, self.inner.queue.mailbox(), self.ticket.as_ref())
+	}
+
+	/// Mark the state as done without any result, because the event could not be handled.
+	///
+	/// Has no effect if the state is already done.
+	pub fn fail(state: &Arc<Mutex<State<R, T>>>, error: SendError) {
+		state.lock().set_error(error)
+	}
+
+	fn set_error(&mut self, error: SendError) {
+		if !self.is_done {
+			self.error = Some(error);
+			self.finish()
+		}
+	}
+
+	/// Cancel the event, because its response is no longer awaited.
+	///
+	/// The event is skipped if it is not being handled yet.
+	fn cancel(&mut self) {
+		if !self.is_done {
+			self.cancelled.store(true, Ordering::Release);
+			if !self.is_started {
+				self.finish()
+			}
+		}
+	}
+
+	/// Mark the state as done, without any result.
+	fn finish(&mut self) {
+		self.is_done = true;
+		self.wait = None;
+		self.release();
+
+		let mut waker = None;
+		std::mem::swap(&mut waker, &mut self.waker);
+		if let Some(waker) = waker {
+			waker.wake()
+		}
+
+		let mut local_waker = None;
+		std::mem::swap(&mut local_waker, &mut self.local_waker);
+		if let Some(local_waker) = local_waker {
+			local_waker.wake()
+		}
+	}
+
 	// The future lifetime must be bound to the receiver lifetime.
 	pub unsafe fn pending<'a, F: 'a + std::future::Future<Output = T>>(state: &Arc<Mutex<State<R, T>>>, future: F) {
 		let mut state = state.lock();
 		state.local_future = Some({ // unsafe part
 			// This is safe because the receiver won't be dropped until the future is completed.
-			std::mem::transmute(Box::pin(future) as Pin<Box<dyn 'a + std::future::Future<Output = T>>>)
+			std::mem::transmute::<Pin<Box<dyn 'a + std::future::Future<Output = T>>>, Pin<Box<dyn 'static + std::future::Future<Output = T>>>>(Box::pin(future))
 		});
 
 		let mut local_waker = None;
@@ -121,6 +426,37 @@ impl<R: ?Sized, T: 'static + Send> State<R, T> {
 			local_waker.wake()
 		}
 	}
+
+	/// Set the future of a reentrant handler, which does not borrow the actor.
+	pub fn reentrant(state: &Arc<Mutex<State<R, T>>>, future: Pin<Box<dyn 'static + std::future::Future<Output = T>>>) {
+		let mut state = state.lock();
+		state.local_future = Some(future);
+		state.is_reentrant = true;
+
+		let mut local_waker = None;
+		std::mem::swap(&mut local_waker, &mut state.local_waker);
+		if let Some(local_waker) = local_waker {
+			local_waker.wake()
+		}
+	}
+}
+
+impl<R: ?Sized, T: 'static + Send> Drop for State<R, T> {
+	fn drop(&mut self) {
+		self.release()
+	}
+}
+
+impl<R: ?Sized, T: 'static + Send> Ticket for Mutex<State<R, T>> {
+	fn drop_event(&self) -> bool {
+		let mut state = self.lock();
+		if state.is_started || state.is_done {
+			false
+		} else {
+			state.set_error(SendError::Full);
+			true
+		}
+	}
 }
 
 pub(crate) struct LocalFuture<R: ?Sized, T: 'static + Send> {
@@ -143,10 +479,20 @@ impl<R: ?Sized, T: 'static + Send> std::future::Future for LocalFuture<R, T> {
 
 		if state.local_future.is_some() {
 			state.local_waker = None;
-			match state.local_future.as_mut().unwrap().as_mut().poll(ctx) {
-				Poll::Pending => Poll::Pending,
-				Poll::Ready(result) => {
+			let previous = state.inner.set_cancellation(Some(state.cancelled.clone()));
+			let actor = if state.is_reentrant { None } else { Some(state.inner.node()) };
+			let previous_actor = deadlock::enter(actor);
+			let local_future = state.local_future.as_mut().unwrap();
+			let poll = panic::catch_unwind(AssertUnwindSafe(|| local_future.as_mut().poll(ctx)));
+			deadlock::enter(previous_actor);
+			state.inner.set_cancellation(previous);
+
+			match poll {
+				Ok(Poll::Pending) => Poll::Pending,
+				Ok(Poll::Ready(result)) => {
 					state.result = Some(result);
+					state.is_done = true;
+					state.wait = None;
 
 					let mut waker = None;
 					std::mem::swap(&mut waker, &mut state.waker);
@@ -154,16 +500,65 @@ impl<R: ?Sized, T: 'static + Send> std::future::Future for LocalFuture<R, T> {
 						waker.wake()
 					}
 
-					unsafe {
-						state.remote.restart();
+					if !state.is_reentrant {
+						// The local future borrows the actor: it must be dropped before the actor
+						// restarts, since the actor may fail.
+						state.local_future = None;
+						unsafe {
+							state.inner.restart();
+						}
+					}
+
+					Poll::Ready(())
+				},
+				Err(_) => {
+					// The handler panicked.
+					state.set_error(SendError::HandlerPanicked);
+					let local_future = state.local_future.take();
+					let is_reentrant = state.is_reentrant;
+					let inner = state.inner.clone();
+					std::mem::drop(state);
+
+					// The local future borrows the actor: it must be dropped before the actor fails.
+					std::mem::drop(local_future);
+					if is_reentrant {
+						inner.fail_when_idle()
+					} else {
+						inner.fail()
 					}
 
 					Poll::Ready(())
 				}
 			}
+		} else if state.is_done {
+			// The event has been handled synchronously, or could not be handled.
+			Poll::Ready(())
 		} else {
 			state.local_waker = Some(ctx.waker().clone());
 			Poll::Pending
 		}
 	}
 }
+
+impl<R: ?Sized, T: 'static + Send> Drop for LocalFuture<R, T> {
+	fn drop(&mut self) {
+		// Make sure the response future does not hang if this future is dropped before
+		// completion, which happens if the processor is dropped or unwinds.
+		let local_future = {
+			let mut state = self.state.lock();
+			if !state.is_done {
+				state.set_error(if std::thread::panicking() {
+					SendError::HandlerPanicked
+				} else {
+					SendError::QueueClosed
+				});
+			}
+
+			// The local future must be dropped in the actor's thread, even if the response is
+			// no longer awaited.
+			state.local_future.take()
+		};
+
+		std::mem::drop(local_future)
+	}
+}
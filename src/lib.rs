@@ -1,4 +1,13 @@
-#![feature(
+//! An actor model implementation for Rust.
+//!
+//! # Nightly ergonomics
+//! By default, this crate builds on stable Rust: handlers receive the actor state as `&mut self`
+//! along with its [`Context`], and pointers are converted to trait objects with the [`upcast!`]
+//! macro.
+//! The `nightly` feature enables the original API, which requires a nightly compiler: handlers
+//! receive a [`Receiver<Self>`](Receiver) as `self`, and pointers are converted to trait objects
+//! by unsized coercion.
+#![cfg_attr(feature = "nightly", feature(
 	// To allow the use of `Receiver<Self>` as `self` type.
 	arbitrary_self_types,
 
@@ -7,18 +16,9 @@
 	coerce_unsized,
 	dispatch_from_dyn,
 
-	// To wrap futures in Future.
-	box_into_pin,
-
-	// To convert `Receiver` into `Local` and `Remote` using black magic.
-	alloc_layout_extra,
-
 	// To avoid `Send` and `Sync` auto implementation.
-	negative_impls,
-
-	// To implement `Hash` for `WeakRemote`.
-	weak_into_raw
-)]
+	negative_impls
+))]
 
 #[macro_use]
 extern crate static_assertions;
@@ -26,31 +26,101 @@ extern crate static_assertions;
 use std::pin::Pin;
 use futures::future::FutureExt;
 
+mod error;
 mod future;
+mod context;
+#[cfg(feature = "nightly")]
 mod receiver;
 mod remote;
 mod local;
 mod pending;
 mod queue;
+mod mailbox;
 mod demux;
 mod emitter;
+mod lifecycle;
+mod supervisor;
+mod runtime;
+mod blocking;
+mod timer;
+mod deadlock;
+mod registry;
 
-pub use future::Future;
+pub use future::{Future, Ask, Timeout};
+pub use error::*;
+pub use context::{Context, AnyContext};
+#[cfg(feature = "nightly")]
 pub use receiver::*;
 pub use remote::*;
 pub use local::*;
 use pending::*;
 pub use queue::*;
+pub use mailbox::{Capacity, Overflow};
 pub use demux::*;
 pub use emitter::*;
+pub use lifecycle::Lifecycle;
+pub use supervisor::*;
+pub use runtime::*;
+pub use timer::{Clock, SystemClock, ManualClock, Timer, Sleep};
+pub use deadlock::{DeadlockPolicy, set_deadlock_policy, deadlock_policy};
+pub use registry::Registry;
+#[cfg(feature = "derive")]
+pub use bottle_derive::{Event, Emitter, actor};
+
+/// Scheduling priority of an event.
+///
+/// Events of higher priority overtake the events of lower priority waiting in the event queue and
+/// in the actor inboxes. Events of the same priority are handled in the order they are sent.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Priority {
+	Low,
+	#[default]
+	Normal,
+	High
+}
 
 pub trait Event: Send {
 	type Response: 'static + Send;
+
+	/// Priority of the event, unless another priority is given when sending it.
+	const PRIORITY: Priority = Priority::Normal;
 }
 
+/// Output of a handler.
+///
+/// New kinds of outputs may be added: matching on an `Output` requires a wildcard arm.
+#[non_exhaustive]
 pub enum Output<'a, T> {
+	/// The response is ready.
 	Now(T),
-	Later(Pin<Box<dyn 'a + std::future::Future<Output = T>>>)
+
+	/// The response is computed by the given future.
+	///
+	/// The future may borrow the actor state: the actor is busy until it completes, and the other
+	/// events sent to the actor wait in its inbox.
+	Later(Pin<Box<dyn 'a + std::future::Future<Output = T>>>),
+
+	/// The response is computed by the given future, while the actor handles other events.
+	///
+	/// The future cannot borrow the actor state: it can only reach the actor by sending it
+	/// events, through the actor pointers. The actor is no longer busy once the handler returns,
+	/// so the other events, including those sent by the future, are handled whenever the future
+	/// is suspended, and may be handled before it completes.
+	/// This allows request/callback protocols between actors, where awaiting a `Later` response
+	/// would deadlock.
+	///
+	/// If the future panics, the event fails with
+	/// [`SendError::HandlerPanicked`], and the actor fails once it is no longer busy.
+	Reentrant(Pin<Box<dyn 'static + std::future::Future<Output = T>>>)
+}
+
+impl<'a, T> Output<'a, T> {
+	/// Create a reentrant output from the given future.
+	///
+	/// See [`Output::Reentrant`].
+	pub fn reentrant<F: 'static + std::future::Future<Output = T>>(future: F) -> Output<'a, T> {
+		Output::Reentrant(future.boxed_local())
+	}
 }
 
 impl<'a, T, F: 'a + std::future::Future<Output = T>> From<F> for Output<'a, T> {
@@ -59,11 +129,237 @@ impl<'a, T, F: 'a + std::future::Future<Output = T>> From<F> for Output<'a, T> {
 	}
 }
 
+#[cfg(feature = "nightly")]
 pub trait Handler<E: Event> {
 	fn handle<'a>(self: Receiver<'a, Self>, event: E) -> Output<'a, E::Response>;
 }
 
+/// Handler of `E` events.
+///
+/// Handlers can be called through `dyn Handler<E>` trait objects, even though `handle` requires
+/// `Self: Sized`: they are dispatched with [`Dispatch`], which is implemented for every handler.
+#[cfg(not(feature = "nightly"))]
+pub trait Handler<E: Event>: Dispatch<E> {
+	fn handle<'a>(&'a mut self, ctx: &'a mut Context<Self>, event: E) -> Output<'a, E::Response> where Self: Sized;
+}
+
+/// Dynamically dispatched handler, implemented for every [`Handler`].
+#[cfg(not(feature = "nightly"))]
+#[doc(hidden)]
+pub trait Dispatch<E: Event> {
+	/// # Safety
+	/// `ctx` must be the context of this actor.
+	unsafe fn dispatch<'a>(&'a mut self, ctx: &'a mut AnyContext, event: E) -> Output<'a, E::Response>;
+}
+
+#[cfg(not(feature = "nightly"))]
+impl<E: Event, T: Handler<E>> Dispatch<E> for T {
+	unsafe fn dispatch<'a>(&'a mut self, ctx: &'a mut AnyContext, event: E) -> Output<'a, E::Response> {
+		self.handle(ctx.cast(), event)
+	}
+}
+
+/// Implement `Handler` by calling a method of the actor.
+///
+/// Used by the `#[actor]` attribute of the `derive` feature: the handler signature depends on
+/// the `nightly` feature of this crate, not of the calling crate.
+#[cfg(feature = "nightly")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __handler {
+	(reference now [$($generics:tt)*] ($actor:ty) ($event:ty) $method:ident [$($bounds:tt)*]) => {
+		impl $($generics)* $crate::Handler<$event> for $actor $($bounds)* {
+			fn handle<'a>(mut self: $crate::Receiver<'a, Self>, event: $event) -> $crate::Output<'a, <$event as $crate::Event>::Response> {
+				$crate::Output::Now(<$actor>::$method(&mut *self, event))
+			}
+		}
+	};
+	(reference later [$($generics:tt)*] ($actor:ty) ($event:ty) $method:ident [$($bounds:tt)*]) => {
+		impl $($generics)* $crate::Handler<$event> for $actor $($bounds)* {
+			fn handle<'a>(mut self: $crate::Receiver<'a, Self>, event: $event) -> $crate::Output<'a, <$event as $crate::Event>::Response> {
+				$crate::Output::Later(::std::boxed::Box::pin(async move {
+					<$actor>::$method(&mut *self, event).await
+				}))
+			}
+		}
+	};
+	(reference reentrant [$($generics:tt)*] ($actor:ty) ($event:ty) $method:ident [$($bounds:tt)*]) => {
+		impl $($generics)* $crate::Handler<$event> for $actor $($bounds)* {
+			fn handle<'a>(mut self: $crate::Receiver<'a, Self>, event: $event) -> $crate::Output<'a, <$event as $crate::Event>::Response> {
+				$crate::Output::reentrant(<$actor>::$method(&mut *self, event))
+			}
+		}
+	};
+	(receiver now [$($generics:tt)*] ($actor:ty) ($event:ty) $method:ident [$($bounds:tt)*]) => {
+		impl $($generics)* $crate::Handler<$event> for $actor $($bounds)* {
+			fn handle<'a>(self: $crate::Receiver<'a, Self>, event: $event) -> $crate::Output<'a, <$event as $crate::Event>::Response> {
+				$crate::Output::Now(<$actor>::$method(self, event))
+			}
+		}
+	};
+	(receiver later [$($generics:tt)*] ($actor:ty) ($event:ty) $method:ident [$($bounds:tt)*]) => {
+		impl $($generics)* $crate::Handler<$event> for $actor $($bounds)* {
+			fn handle<'a>(self: $crate::Receiver<'a, Self>, event: $event) -> $crate::Output<'a, <$event as $crate::Event>::Response> {
+				$crate::Output::Later(::std::boxed::Box::pin(<$actor>::$method(self, event)))
+			}
+		}
+	};
+	(receiver reentrant [$($generics:tt)*] ($actor:ty) ($event:ty) $method:ident [$($bounds:tt)*]) => {
+		impl $($generics)* $crate::Handler<$event> for $actor $($bounds)* {
+			fn handle<'a>(self: $crate::Receiver<'a, Self>, event: $event) -> $crate::Output<'a, <$event as $crate::Event>::Response> {
+				$crate::Output::reentrant(<$actor>::$method(self, event))
+			}
+		}
+	};
+	(context $execution:ident [$($generics:tt)*] ($actor:ty) ($event:ty) $method:ident [$($bounds:tt)*]) => {
+		compile_error!("handlers cannot take a `Context` with the `nightly` feature: take `self: Receiver<Self>` instead");
+	};
+}
+
+/// Implement `Handler` by calling a method of the actor.
+///
+/// Used by the `#[actor]` attribute of the `derive` feature: the handler signature depends on
+/// the `nightly` feature of this crate, not of the calling crate.
+#[cfg(not(feature = "nightly"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __handler {
+	(reference now [$($generics:tt)*] ($actor:ty) ($event:ty) $method:ident [$($bounds:tt)*]) => {
+		impl $($generics)* $crate::Handler<$event> for $actor $($bounds)* {
+			fn handle<'a>(&'a mut self, _ctx: &'a mut $crate::Context<Self>, event: $event) -> $crate::Output<'a, <$event as $crate::Event>::Response> {
+				$crate::Output::Now(<$actor>::$method(self, event))
+			}
+		}
+	};
+	(reference later [$($generics:tt)*] ($actor:ty) ($event:ty) $method:ident [$($bounds:tt)*]) => {
+		impl $($generics)* $crate::Handler<$event> for $actor $($bounds)* {
+			fn handle<'a>(&'a mut self, _ctx: &'a mut $crate::Context<Self>, event: $event) -> $crate::Output<'a, <$event as $crate::Event>::Response> {
+				$crate::Output::Later(::std::boxed::Box::pin(<$actor>::$method(self, event)))
+			}
+		}
+	};
+	(reference reentrant [$($generics:tt)*] ($actor:ty) ($event:ty) $method:ident [$($bounds:tt)*]) => {
+		impl $($generics)* $crate::Handler<$event> for $actor $($bounds)* {
+			fn handle<'a>(&'a mut self, _ctx: &'a mut $crate::Context<Self>, event: $event) -> $crate::Output<'a, <$event as $crate::Event>::Response> {
+				$crate::Output::reentrant(<$actor>::$method(self, event))
+			}
+		}
+	};
+	(context now [$($generics:tt)*] ($actor:ty) ($event:ty) $method:ident [$($bounds:tt)*]) => {
+		impl $($generics)* $crate::Handler<$event> for $actor $($bounds)* {
+			fn handle<'a>(&'a mut self, ctx: &'a mut $crate::Context<Self>, event: $event) -> $crate::Output<'a, <$event as $crate::Event>::Response> {
+				$crate::Output::Now(<$actor>::$method(self, ctx, event))
+			}
+		}
+	};
+	(context later [$($generics:tt)*] ($actor:ty) ($event:ty) $method:ident [$($bounds:tt)*]) => {
+		impl $($generics)* $crate::Handler<$event> for $actor $($bounds)* {
+			fn handle<'a>(&'a mut self, ctx: &'a mut $crate::Context<Self>, event: $event) -> $crate::Output<'a, <$event as $crate::Event>::Response> {
+				$crate::Output::Later(::std::boxed::Box::pin(<$actor>::$method(self, ctx, event)))
+			}
+		}
+	};
+	(context reentrant [$($generics:tt)*] ($actor:ty) ($event:ty) $method:ident [$($bounds:tt)*]) => {
+		impl $($generics)* $crate::Handler<$event> for $actor $($bounds)* {
+			fn handle<'a>(&'a mut self, ctx: &'a mut $crate::Context<Self>, event: $event) -> $crate::Output<'a, <$event as $crate::Event>::Response> {
+				$crate::Output::reentrant(<$actor>::$method(self, ctx, event))
+			}
+		}
+	};
+	(receiver $execution:ident [$($generics:tt)*] ($actor:ty) ($event:ty) $method:ident [$($bounds:tt)*]) => {
+		compile_error!("`self: Receiver<Self>` handlers require the `nightly` feature");
+	};
+}
+
+/// Handler methods rejected by the `#[actor]` attribute.
+///
+/// ```
+/// # use bottle::{Context, Event};
+/// # #[derive(Event)] struct Ping;
+/// struct Actor;
+///
+/// #[bottle::actor]
+/// impl Actor {
+///     #[handle(reentrant)]
+///     fn ping(&mut self, _ctx: &mut Context<Self>, _event: Ping) -> impl 'static + std::future::Future<Output = ()> {
+///         async {}
+///     }
+/// }
+/// ```
+///
+/// Reentrant handler methods cannot be `async`:
+/// ```compile_fail
+/// # use bottle::{Context, Event};
+/// # #[derive(Event)] struct Ping;
+/// struct Actor;
+///
+/// #[bottle::actor]
+/// impl Actor {
+///     #[handle(reentrant)]
+///     async fn ping(&mut self, _event: Ping) {}
+/// }
+/// ```
+///
+/// The future of a reentrant handler method cannot borrow the actor state:
+/// ```compile_fail
+/// # use bottle::{Context, Event};
+/// # #[derive(Event)] struct Ping;
+/// struct Actor(u32);
+///
+/// #[bottle::actor]
+/// impl Actor {
+///     #[handle(reentrant)]
+///     fn ping(&mut self, _event: Ping) -> impl '_ + std::future::Future<Output = ()> {
+///         async move { self.0 += 1 }
+///     }
+/// }
+/// ```
+///
+/// Unknown options are rejected:
+/// ```compile_fail
+/// # use bottle::{Context, Event};
+/// # #[derive(Event)] struct Ping;
+/// struct Actor;
+///
+/// #[bottle::actor]
+/// impl Actor {
+///     #[handle(later)]
+///     fn ping(&mut self, _event: Ping) {}
+/// }
+/// ```
+///
+/// Handler methods cannot take `self` by value:
+/// ```compile_fail
+/// # use bottle::{Context, Event};
+/// # #[derive(Event)] struct Ping;
+/// struct Actor;
+///
+/// #[bottle::actor]
+/// impl Actor {
+///     #[handle]
+///     fn ping(self, _event: Ping) {}
+/// }
+/// ```
+///
+/// Handler methods cannot be generic:
+/// ```compile_fail
+/// # use bottle::{Context, Event};
+/// # #[derive(Event)] struct Ping;
+/// struct Actor;
+///
+/// #[bottle::actor]
+/// impl Actor {
+///     #[handle]
+///     fn ping<T>(&mut self, _event: Ping) {}
+/// }
+/// ```
+#[cfg(all(doctest, feature = "derive", not(feature = "nightly")))]
+mod actor_errors {}
+
 /// A trait for thread local values, attached to an `EventQueue`.
+///
+/// # Safety
+/// The value must reside in the thread processing the returned queue.
 pub unsafe trait ThreadLocal {
 	fn queue(&self) -> &EventQueueRef;
 }
@@ -0,0 +1,189 @@
+use std::sync::Arc;
+use std::pin::Pin;
+use std::future::Future;
+use parking_lot::Mutex;
+use crate::{Output, Inner, Pending, SendError, Priority, future::{self, LocalFuture}};
+#[cfg(feature = "nightly")]
+use crate::Receiver;
+#[cfg(not(feature = "nightly"))]
+use crate::Context;
+
+/// Actor lifecycle hooks.
+///
+/// Actors implementing this trait must be created with [`Remote::spawn`](crate::Remote::spawn)
+/// or [`Remote::spawn_from`](crate::Remote::spawn_from) for the hooks to be called.
+/// Every hook is called by the actor's queue processor, in the actor's thread, and may return an
+/// asynchronous output, in which case the actor does not handle any other event until the
+/// hook completes.
+///
+/// All the hooks do nothing by default.
+#[cfg(feature = "nightly")]
+pub trait Lifecycle {
+	/// Called when the actor is attached to its queue, before any event is handled.
+	fn started<'a>(self: Receiver<'a, Self>) -> Output<'a, ()> {
+		Output::Now(())
+	}
+
+	/// Called when the actor is stopped, once every event already sent to the actor has been
+	/// handled.
+	///
+	/// An actor is stopped either explicitly with [`Remote::stop`](crate::Remote::stop) or
+	/// [`Receiver::stop`], or when the last `Remote` or `Local` pointer to
+	/// the actor has been dropped.
+	fn stopping<'a>(self: Receiver<'a, Self>) -> Output<'a, ()> {
+		Output::Now(())
+	}
+
+	/// Called after `stopping` has completed, right before the actor state is dropped.
+	///
+	/// Events received by the actor from now on are discarded.
+	fn stopped<'a>(self: Receiver<'a, Self>) -> Output<'a, ()> {
+		Output::Now(())
+	}
+}
+
+/// Actor lifecycle hooks.
+///
+/// Actors implementing this trait must be created with [`Remote::spawn`](crate::Remote::spawn)
+/// or [`Remote::spawn_from`](crate::Remote::spawn_from) for the hooks to be called.
+/// Every hook is called by the actor's queue processor, in the actor's thread, and may return an
+/// asynchronous output, in which case the actor does not handle any other event until the
+/// hook completes.
+///
+/// All the hooks do nothing by default.
+#[cfg(not(feature = "nightly"))]
+pub trait Lifecycle {
+	/// Called when the actor is attached to its queue, before any event is handled.
+	fn started<'a>(&'a mut self, _ctx: &'a mut Context<Self>) -> Output<'a, ()> {
+		Output::Now(())
+	}
+
+	/// Called when the actor is stopped, once every event already sent to the actor has been
+	/// handled.
+	///
+	/// An actor is stopped either explicitly with [`Remote::stop`](crate::Remote::stop) or
+	/// [`Context::stop`], or when the last `Remote` or `Local` pointer to the actor has been
+	/// dropped.
+	fn stopping<'a>(&'a mut self, _ctx: &'a mut Context<Self>) -> Output<'a, ()> {
+		Output::Now(())
+	}
+
+	/// Called after `stopping` has completed, right before the actor state is dropped.
+	///
+	/// Events received by the actor from now on are discarded.
+	fn stopped<'a>(&'a mut self, _ctx: &'a mut Context<Self>) -> Output<'a, ()> {
+		Output::Now(())
+	}
+}
+
+#[cfg(feature = "nightly")]
+type Hook<T> = for<'a> fn(Receiver<'a, T>) -> Output<'a, ()>;
+
+#[cfg(not(feature = "nightly"))]
+type Hook<T> = for<'a> fn(&'a mut T, &'a mut Context<T>) -> Output<'a, ()>;
+
+/// Create the release request of an actor without lifecycle hooks.
+///
+/// This request simply drops the actor state.
+pub(crate) fn release<T: 'static>(inner: &Arc<Inner<T>>) -> Box<dyn Pending> {
+	Box::new(Transition::new(inner.clone(), Vec::new(), true))
+}
+
+/// Attach the lifecycle hooks of `T` to the given actor.
+///
+/// This requests the `started` hook to be called, and replaces the release request with one that
+/// calls `stopping` and `stopped` before dropping the actor state.
+pub(crate) fn attach<T: 'static + Lifecycle>(inner: &Arc<Inner<T>>) {
+	inner.queue.push_any(Box::new(Transition::new(inner.clone(), vec![T::started], false)));
+	inner.set_release(Box::new(Transition::new(inner.clone(), vec![T::stopping, T::stopped], true)));
+}
+
+/// Call the given hook on the actor.
+///
+/// Must be called from the actor's thread, while the actor is busy.
+#[cfg(feature = "nightly")]
+async unsafe fn call<T: ?Sized>(inner: &Arc<Inner<T>>, hook: Hook<T>) {
+	match hook(Receiver::new(Arc::as_ptr(inner))) {
+		Output::Now(()) => (),
+		Output::Later(future) => future.await,
+		Output::Reentrant(future) => future.await
+	}
+}
+
+/// Call the given hook on the actor.
+///
+/// Must be called from the actor's thread, while the actor is busy.
+#[cfg(not(feature = "nightly"))]
+async unsafe fn call<T: ?Sized>(inner: &Arc<Inner<T>>, hook: Hook<T>) {
+	let (data, context) = inner.state();
+	match hook(data, context) {
+		Output::Now(()) => (),
+		Output::Later(future) => future.await,
+		Output::Reentrant(future) => future.await
+	}
+}
+
+/// Sequence of lifecycle hooks to call on an actor.
+pub(crate) struct Transition<T: ?Sized> {
+	inner: Arc<Inner<T>>,
+	hooks: Vec<Hook<T>>,
+	stop: bool,
+	state: Arc<Mutex<future::State<T, ()>>>
+}
+
+// The actor is only accessed from the processor's thread, once the transition is posted.
+unsafe impl<T: ?Sized> Send for Transition<T> {}
+
+impl<T: ?Sized> Transition<T> {
+	fn new(inner: Arc<Inner<T>>, hooks: Vec<Hook<T>>, stop: bool) -> Transition<T> {
+		Transition {
+			state: future::State::new(inner.clone(), "lifecycle hook"),
+			inner,
+			hooks,
+			stop
+		}
+	}
+}
+
+impl<T: 'static + ?Sized> Pending for Transition<T> {
+	fn post(self: Box<Self>) -> Option<Pin<Box<dyn Future<Output = ()>>>> {
+		let inner = self.inner.clone();
+		let future = LocalFuture::new(self.state.clone());
+		inner.post_any(self);
+		Some(Box::pin(future))
+	}
+
+	fn process(self: Box<Self>) {
+		let Transition { inner, hooks, stop, state } = *self;
+		inner.actor.borrow_mut().is_busy = true;
+
+		unsafe {
+			// The future holds its own reference to the actor, and the actor is busy until it
+			// completes.
+			future::State::pending(&state, async move {
+				for hook in hooks {
+					call(&inner, hook).await
+				}
+
+				if stop {
+					inner.drop_data();
+					inner.clear_monitors()
+				}
+			})
+		}
+	}
+
+	fn priority(&self) -> Priority {
+		if self.stop {
+			// Events sent before the actor is stopped are handled first.
+			Priority::Low
+		} else {
+			// The actor is started before handling any event.
+			Priority::High
+		}
+	}
+
+	fn discard(self: Box<Self>, error: SendError) {
+		future::State::fail(&self.state, error)
+	}
+}
@@ -1,7 +1,9 @@
+#[cfg(feature = "nightly")]
 use std::marker::Unsize;
+#[cfg(feature = "nightly")]
 use std::ops::{DispatchFromDyn, CoerceUnsized};
 use std::sync::Arc;
-use crate::{Remote, Event, Handler, Future, Inner, ThreadLocal, EventQueueRef};
+use crate::{Remote, Event, Handler, Future, Priority, Inner, ThreadLocal, EventQueueRef, ActorId};
 
 /// A reference to a local actor.
 ///
@@ -12,23 +14,32 @@ pub struct Local<T: ?Sized> {
 	inner: Arc<Inner<T>>
 }
 
+#[cfg(feature = "nightly")]
 impl<T: ?Sized + Unsize<U>, U: ?Sized> CoerceUnsized<Local<U>> for Local<T> {}
+#[cfg(feature = "nightly")]
 impl<T: ?Sized + Unsize<U>, U: ?Sized> DispatchFromDyn<Local<U>> for Local<T> {}
 
+#[cfg(feature = "nightly")]
 impl<T: ?Sized> !Send for Local<T> {}
+#[cfg(feature = "nightly")]
 impl<T: ?Sized> !Sync for Local<T> {}
 assert_not_impl_any!(Local<()>: Send, Sync);
 
 impl<T: ?Sized> Clone for Local<T> {
 	fn clone(&self) -> Local<T> {
-		Local {
-			inner: self.inner.clone()
-		}
+		Local::from_inner(self.inner.clone())
+	}
+}
+
+impl<T: ?Sized> Drop for Local<T> {
+	fn drop(&mut self) {
+		self.inner.drop_handle()
 	}
 }
 
 impl<T: ?Sized> Local<T> {
 	pub(crate) fn from_inner(inner: Arc<Inner<T>>) -> Local<T> {
+		inner.acquire();
 		Local {
 			inner
 		}
@@ -38,9 +49,33 @@ impl<T: ?Sized> Local<T> {
 		Remote::from_inner(self.inner.clone())
 	}
 
-	pub fn send<E: Event>(&self, event: E) -> Future<T, E::Response> where E: 'static, T: 'static + Handler<E> {
+	/// Identifier of the actor.
+	pub fn id(&self) -> ActorId {
+		self.inner.id()
+	}
+
+	/// Human-readable name of the actor, if any.
+	///
+	/// See [`Remote::set_name`].
+	pub fn name(&self) -> Option<String> {
+		self.inner.name()
+	}
+
+	pub fn send<E: 'static + Event>(&self, event: E) -> Future<T, E::Response> where T: 'static + Handler<E> {
 		self.inner.queue.push(self.as_remote(), event)
 	}
+
+	/// Send an event to the actor without waiting for its response.
+	///
+	/// See [`Remote::tell`].
+	pub fn tell<E: 'static + Event>(&self, event: E) where T: 'static + Handler<E> {
+		self.inner.queue.notify(self.as_remote(), event, E::PRIORITY)
+	}
+
+	/// Send an event to the actor with the given priority, instead of [`Event::PRIORITY`].
+	pub fn send_with_priority<E: 'static + Event>(&self, event: E, priority: Priority) -> Future<T, E::Response> where T: 'static + Handler<E> {
+		self.inner.queue.push_with_priority(self.as_remote(), event, priority)
+	}
 }
 
 unsafe impl<T: ?Sized> ThreadLocal for Local<T> {
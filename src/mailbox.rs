@@ -0,0 +1,227 @@
+use std::sync::Weak;
+use std::task::Waker;
+use std::collections::VecDeque;
+use parking_lot::Mutex;
+
+/// What to do when an event is sent to a full mailbox.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Overflow {
+	/// The sender waits for the mailbox to have some room.
+	///
+	/// The event is delivered once the returned future is polled and the mailbox is no longer
+	/// full.
+	Wait,
+
+	/// The oldest event of the mailbox that is not being handled yet is dropped to make room for
+	/// the new one.
+	DropOldest,
+
+	/// The new event is dropped.
+	DropNewest
+}
+
+/// Capacity of a mailbox.
+///
+/// A mailbox counts the events sent to an actor, or to the actors of an event queue, that are not
+/// being handled yet.
+/// Dropped events resolve into [`SendError::Full`](crate::SendError::Full).
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Capacity {
+	/// Maximum number of events in the mailbox.
+	pub limit: usize,
+
+	/// What to do when the mailbox is full.
+	pub overflow: Overflow
+}
+
+impl Capacity {
+	pub fn new(limit: usize, overflow: Overflow) -> Capacity {
+		Capacity {
+			limit,
+			overflow
+		}
+	}
+}
+
+/// An event in a mailbox, that can be dropped.
+pub(crate) trait Ticket: Send + Sync {
+	/// Drop the event, unless it is already being handled or done.
+	///
+	/// Returns `true` if the event has been dropped.
+	fn drop_event(&self) -> bool;
+}
+
+struct State {
+	capacity: Option<Capacity>,
+	len: usize,
+
+	/// Senders waiting for some room.
+	waiters: Vec<Waker>,
+
+	/// Events of the mailbox, in the order they have been sent.
+	///
+	/// Only used with the `DropOldest` policy.
+	events: VecDeque<Weak<dyn Ticket>>
+}
+
+/// Result of an attempt to put an event into a mailbox.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Acquire {
+	Acquired,
+	Full(Overflow)
+}
+
+pub(crate) struct Mailbox {
+	state: Mutex<State>
+}
+
+impl Mailbox {
+	pub fn new(capacity: Option<Capacity>) -> Mailbox {
+		Mailbox {
+			state: Mutex::new(State {
+				capacity,
+				len: 0,
+				waiters: Vec::new(),
+				events: VecDeque::new()
+			})
+		}
+	}
+
+	pub fn capacity(&self) -> Option<Capacity> {
+		self.state.lock().capacity
+	}
+
+	/// Number of events in the mailbox.
+	pub fn len(&self) -> usize {
+		self.state.lock().len
+	}
+
+	pub fn set_capacity(&self, capacity: Option<Capacity>) {
+		let waiters = {
+			let mut state = self.state.lock();
+			state.capacity = capacity;
+			state.events.clear();
+			std::mem::take(&mut state.waiters)
+		};
+
+		for waker in waiters {
+			waker.wake()
+		}
+	}
+
+	/// Take some room in the mailbox for the given event.
+	///
+	/// With the `DropOldest` policy, the oldest events are dropped as needed. Events without
+	/// ticket cannot be dropped.
+	/// With the `Wait` policy, the given waker is called once some room is made.
+	pub fn acquire(&self, ticket: Option<&Weak<dyn Ticket>>, waker: Option<&Waker>) -> Acquire {
+		loop {
+			let oldest = {
+				let mut state = self.state.lock();
+				let capacity = match state.capacity {
+					Some(capacity) => capacity,
+					None => {
+						state.len += 1;
+						return Acquire::Acquired
+					}
+				};
+
+				if state.len < capacity.limit {
+					state.len += 1;
+					if capacity.overflow == Overflow::DropOldest {
+						if let Some(ticket) = ticket {
+							state.events.push_back(ticket.clone());
+						}
+					}
+
+					return Acquire::Acquired
+				}
+
+				match capacity.overflow {
+					Overflow::Wait => {
+						// A sender is polled again on spurious wake-ups: its waker is only
+						// registered once.
+						if let Some(waker) = waker {
+							if !state.waiters.iter().any(|waiter| waiter.will_wake(waker)) {
+								state.waiters.push(waker.clone())
+							}
+						}
+
+						return Acquire::Full(Overflow::Wait)
+					},
+					Overflow::DropNewest => return Acquire::Full(Overflow::DropNewest),
+					Overflow::DropOldest => match state.events.pop_front() {
+						Some(oldest) => oldest,
+						None => return Acquire::Full(Overflow::DropOldest)
+					}
+				}
+			};
+
+			// Dropping the event releases its room, if it is not already being handled.
+			if let Some(oldest) = oldest.upgrade() {
+				oldest.drop_event();
+			}
+		}
+	}
+
+	/// Release the room taken with `acquire` for the given event.
+	pub fn release(&self, ticket: Option<&Weak<dyn Ticket>>) {
+		let waiters = {
+			let mut state = self.state.lock();
+			state.len -= 1;
+			if let Some(ticket) = ticket {
+				if let Some(i) = state.events.iter().position(|event| event.ptr_eq(ticket)) {
+					state.events.remove(i);
+				}
+			}
+
+			std::mem::take(&mut state.waiters)
+		};
+
+		for waker in waiters {
+			waker.wake()
+		}
+	}
+}
+
+/// Room taken by an event in the mailboxes of its receiver: the actor mailbox and the queue
+/// mailbox.
+#[derive(Default)]
+pub(crate) struct Reservation {
+	actor: bool,
+	queue: bool
+}
+
+impl Reservation {
+	/// Take some room in the actor mailbox, then in the queue mailbox.
+	///
+	/// Nothing is taken if one of them is full.
+	pub fn acquire(&mut self, actor: &Mailbox, queue: &Mailbox, ticket: Option<&Weak<dyn Ticket>>, waker: Option<&Waker>) -> Acquire {
+		match actor.acquire(ticket, waker) {
+			Acquire::Acquired => self.actor = true,
+			full => return full
+		}
+
+		match queue.acquire(ticket, waker) {
+			Acquire::Acquired => self.queue = true,
+			full => {
+				self.actor = false;
+				actor.release(ticket);
+				return full
+			}
+		}
+
+		Acquire::Acquired
+	}
+
+	/// Release the room taken with `acquire`, if any.
+	pub fn release(&mut self, actor: &Mailbox, queue: &Mailbox, ticket: Option<&Weak<dyn Ticket>>) {
+		if std::mem::take(&mut self.actor) {
+			actor.release(ticket)
+		}
+
+		if std::mem::take(&mut self.queue) {
+			queue.release(ticket)
+		}
+	}
+}
@@ -1,13 +1,32 @@
 use std::sync::Arc;
+use std::cell::RefCell;
+use std::mem::ManuallyDrop;
+use std::ops::Deref;
+use std::thread::{self, ThreadId};
 use std::pin::Pin;
 use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::task::{Waker, Context, Poll};
 use parking_lot::Mutex;
-use crate::{Output, Event, Handler, Remote, future};
+use crate::{Output, Event, Handler, Remote, Inner, SendError, Priority, future, deadlock};
+use crate::mailbox::{Acquire, Reservation};
 
 pub(crate) trait Pending: Send {
 	fn post(self: Box<Self>) -> Option<Pin<Box<dyn Future<Output = ()>>>>;
 
 	fn process(self: Box<Self>);
+
+	fn priority(&self) -> Priority;
+
+	/// Take some room for the event in the mailboxes of its receiver.
+	///
+	/// Only events sent by the user are counted.
+	fn reserve(&self, _waker: Option<&Waker>) -> Acquire {
+		Acquire::Acquired
+	}
+
+	/// Drop the pending event without processing it, because it cannot be handled.
+	fn discard(self: Box<Self>, error: SendError);
 }
 
 pub(crate) struct Initialize<T, F: Send + FnOnce() -> T> {
@@ -25,31 +44,38 @@ impl<T, F: Send + FnOnce() -> T> Initialize<T, F> {
 
 impl<T: 'static, F: 'static + Send + FnOnce() -> T> Pending for Initialize<T, F> {
 	fn post(self: Box<Self>) -> Option<Pin<Box<dyn Future<Output = ()>>>> {
-		let remote = self.remote.clone();
-		remote.post_any(self);
+		// The initialization is never delayed: the other events wait for it.
+		self.process();
 		None
 	}
 
 	fn process(self: Box<Self>) {
-		unsafe {
-			let mut actor = self.remote.inner.actor.borrow_mut();
-			actor.init((self.constructor)())
-		}
+		let Initialize { remote, constructor } = *self;
+		remote.inner.construct(constructor)
+	}
+
+	fn priority(&self) -> Priority {
+		// The events sent in the meantime wait for the initialization anyway.
+		Priority::High
 	}
+
+	fn discard(self: Box<Self>, _error: SendError) {}
 }
 
 pub(crate) struct ToReceive<E: Event, T: ?Sized + Handler<E>> {
 	receiver: Remote<T>,
 	event: E,
+	priority: Priority,
 	future: Arc<Mutex<future::State<T, E::Response>>>,
 }
 
 impl<E: Event, T: ?Sized + Handler<E>> ToReceive<E, T> {
-	pub fn new(receiver: Remote<T>, event: E) -> ToReceive<E, T> {
+	pub fn new(receiver: Remote<T>, event: E, priority: Priority) -> ToReceive<E, T> {
 		ToReceive {
 			receiver: receiver.clone(),
 			event,
-			future: future::State::new(receiver)
+			priority,
+			future: future::State::new(receiver.inner.clone(), std::any::type_name::<E>())
 		}
 	}
 
@@ -65,24 +91,332 @@ impl<E: 'static + Event, T: 'static + ?Sized + Handler<E>> Pending for ToReceive
 	}
 
 	fn process(self: Box<Self>) {
-		let mut actor = self.receiver.inner.actor.borrow_mut();
-		actor.is_busy = true;
-
-		{
-			let result = unsafe { actor.post(self.event) };
-			match result {
-				Output::Now(result) => {
-					future::State::set(&self.future, result);
+		let ToReceive { receiver, event, future, .. } = *self;
+
+		let cancellation = match future::State::start(&future) {
+			Some(cancellation) => cancellation,
+			// The event has been dropped by the mailbox overflow policy, or cancelled.
+			None => return
+		};
+
+		receiver.inner.actor.borrow_mut().is_busy = true;
+
+		let previous = receiver.inner.set_cancellation(Some(cancellation));
+		let result = panic::catch_unwind(AssertUnwindSafe(|| unsafe { Inner::post(&receiver.inner, event) }));
+		receiver.inner.set_cancellation(previous);
+		match result {
+			Ok(Output::Now(result)) => {
+				future::State::set(&future, result);
+				receiver.inner.idle();
+			},
+			Ok(Output::Later(later)) => unsafe {
+				// This is safe because the actor is embedded in the future: it won't be dropped
+				// until it is completed.
+				future::State::pending(&future, later)
+			},
+			Ok(Output::Reentrant(reentrant)) => {
+				future::State::reentrant(&future, reentrant);
+				receiver.inner.idle();
+			},
+			Err(_) => {
+				future::State::fail(&future, SendError::HandlerPanicked);
+				receiver.inner.fail()
+			}
+		}
+	}
+
+	fn priority(&self) -> Priority {
+		self.priority
+	}
+
+	fn reserve(&self, waker: Option<&Waker>) -> Acquire {
+		future::State::reserve(&self.future, waker)
+	}
+
+	fn discard(self: Box<Self>, error: SendError) {
+		future::State::fail(&self.future, error)
+	}
+}
+
+/// Event sent without waiting for its response.
+///
+/// Unlike `ToReceive`, there is no response state: the output of the handler is dropped.
+pub(crate) struct ToNotify<E: Event, T: ?Sized + Handler<E>> {
+	receiver: Remote<T>,
+	event: E,
+	priority: Priority,
+	reservation: Reservation,
+
+	/// Where to hand the handler future over to the processor, if the event waits in the inbox.
+	handoff: Option<Arc<ThreadBound<Handoff>>>
+}
+
+impl<E: Event, T: ?Sized + Handler<E>> ToNotify<E, T> {
+	pub fn new(receiver: Remote<T>, event: E, priority: Priority) -> ToNotify<E, T> {
+		ToNotify {
+			receiver,
+			event,
+			priority,
+			reservation: Reservation::default(),
+			handoff: None
+		}
+	}
+
+	/// Take some room for the event in the mailboxes of its receiver.
+	///
+	/// The event cannot be dropped by the `DropOldest` policy, and never waits.
+	pub fn try_reserve(&mut self) -> Acquire {
+		let inner = &self.receiver.inner;
+		self.reservation.acquire(&inner.mailbox, inner.queue.mailbox(), None, None)
+	}
+}
+
+impl<E: 'static + Event, T: 'static + ?Sized + Handler<E>> ToNotify<E, T> {
+	/// Call the handler.
+	///
+	/// Returns the handler future if the handler is asynchronous.
+	fn handle(self) -> Option<Pin<Box<dyn Future<Output = ()>>>> {
+		let ToNotify { receiver, event, mut reservation, .. } = self;
+		let inner = &receiver.inner;
+		reservation.release(&inner.mailbox, inner.queue.mailbox(), None);
+
+		inner.actor.borrow_mut().is_busy = true;
+
+		let previous = inner.set_cancellation(None);
+		let result = panic::catch_unwind(AssertUnwindSafe(|| unsafe {
+			match Inner::post(inner, event) {
+				Output::Now(_) => None,
+				Output::Later(later) => {
+					// This is safe because the actor is embedded in the future: it won't be
+					// dropped until it is completed.
+					Some((std::mem::transmute::<Pin<Box<dyn '_ + Future<Output = E::Response>>>, Pin<Box<dyn 'static + Future<Output = E::Response>>>>(later), false))
 				},
-				Output::Later(later) => unsafe {
-					// This is safe because the actor is embedded in the future: it won't be dropped
-					// until it is completed.
-					future::State::pending(&self.future, later);
-					return
+				Output::Reentrant(reentrant) => Some((reentrant, true))
+			}
+		}));
+		inner.set_cancellation(previous);
+
+		match result {
+			Ok(None) => {
+				inner.idle();
+				None
+			},
+			Ok(Some((future, is_reentrant))) => {
+				if is_reentrant && inner.idle() {
+					return None
 				}
+
+				Some(Box::pin(Notified {
+					receiver,
+					future: Some(future),
+					is_reentrant
+				}))
+			},
+			Err(_) => {
+				inner.fail();
+				None
 			}
 		}
+	}
+}
+
+impl<E: 'static + Event, T: 'static + ?Sized + Handler<E>> Pending for ToNotify<E, T> {
+	fn post(mut self: Box<Self>) -> Option<Pin<Box<dyn Future<Output = ()>>>> {
+		let inner = self.receiver.inner.clone();
+		if inner.is_available() {
+			self.handle()
+		} else {
+			let handoff = Arc::new(Handoff::new());
+			self.handoff = Some(handoff.clone());
+			inner.post_any(self);
+			Some(Box::pin(HandoffFuture(handoff)))
+		}
+	}
 
-		actor.is_busy = false;
+	fn process(mut self: Box<Self>) {
+		// Events are only processed outside of `post` once they have waited in the inbox.
+		let handoff = self.handoff.take().expect("buffered notification without handoff");
+		handoff.complete(self.handle())
+	}
+
+	fn priority(&self) -> Priority {
+		self.priority
+	}
+
+	fn discard(mut self: Box<Self>, _error: SendError) {
+		let inner = &self.receiver.inner;
+		self.reservation.release(&inner.mailbox, inner.queue.mailbox(), None);
+		if let Some(handoff) = self.handoff.take() {
+			handoff.complete(None)
+		}
+	}
+}
+
+/// Asynchronous handler of a notification.
+struct Notified<T: ?Sized, R> {
+	receiver: Remote<T>,
+	future: Option<Pin<Box<dyn Future<Output = R>>>>,
+
+	/// The future does not borrow the actor.
+	is_reentrant: bool
+}
+
+impl<T: ?Sized, R> Future for Notified<T, R> {
+	type Output = ();
+
+	fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+		let this = &mut *self;
+		let future = match &mut this.future {
+			Some(future) => future,
+			None => return Poll::Ready(())
+		};
+
+		let inner = &this.receiver.inner;
+		let previous = inner.set_cancellation(None);
+		let previous_actor = deadlock::enter(if this.is_reentrant { None } else { Some(inner.node()) });
+		let poll = panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(ctx)));
+		deadlock::enter(previous_actor);
+		inner.set_cancellation(previous);
+
+		// The future borrows the actor: it must be dropped before the actor restarts or fails.
+		match poll {
+			Ok(Poll::Pending) => return Poll::Pending,
+			Ok(Poll::Ready(_)) => {
+				this.future = None;
+				if !this.is_reentrant {
+					unsafe {
+						inner.restart()
+					}
+				}
+			},
+			Err(_) => {
+				this.future = None;
+				if this.is_reentrant {
+					inner.fail_when_idle()
+				} else {
+					inner.fail()
+				}
+			}
+		}
+
+		Poll::Ready(())
+	}
+}
+
+enum HandoffState {
+	Waiting(Option<Waker>),
+	Running(Pin<Box<dyn Future<Output = ()>>>),
+	Done
+}
+
+/// A value that can be sent to other threads, but only accessed in the thread that created it.
+///
+/// Accessing the value from another thread panics, and dropping it there leaks it.
+struct ThreadBound<T> {
+	thread: ThreadId,
+	value: ManuallyDrop<T>
+}
+
+// The value is only accessed, and dropped, in its thread.
+unsafe impl<T> Send for ThreadBound<T> {}
+unsafe impl<T> Sync for ThreadBound<T> {}
+
+impl<T> ThreadBound<T> {
+	fn new(value: T) -> ThreadBound<T> {
+		ThreadBound {
+			thread: thread::current().id(),
+			value: ManuallyDrop::new(value)
+		}
+	}
+
+	fn is_local(&self) -> bool {
+		thread::current().id() == self.thread
+	}
+}
+
+impl<T> Deref for ThreadBound<T> {
+	type Target = T;
+
+	/// # Panics
+	/// Panics if called from another thread than the one that created the value.
+	fn deref(&self) -> &T {
+		assert!(self.is_local(), "thread bound value accessed from another thread");
+		&self.value
+	}
+}
+
+impl<T> Drop for ThreadBound<T> {
+	fn drop(&mut self) {
+		if self.is_local() {
+			unsafe {
+				ManuallyDrop::drop(&mut self.value)
+			}
+		}
+	}
+}
+
+/// Slot through which a notification waiting in the inbox hands its handler future over to the
+/// processor.
+///
+/// It is created and only accessed in the processor's thread.
+struct Handoff {
+	state: RefCell<HandoffState>
+}
+
+impl Handoff {
+	fn new() -> ThreadBound<Handoff> {
+		ThreadBound::new(Handoff {
+			state: RefCell::new(HandoffState::Waiting(None))
+		})
+	}
+
+	fn complete(&self, future: Option<Pin<Box<dyn Future<Output = ()>>>>) {
+		let waker = {
+			let mut state = self.state.borrow_mut();
+			let waker = match &mut *state {
+				HandoffState::Waiting(waker) => waker.take(),
+				_ => None
+			};
+
+			*state = match future {
+				Some(future) => HandoffState::Running(future),
+				None => HandoffState::Done
+			};
+
+			waker
+		};
+
+		if let Some(waker) = waker {
+			waker.wake()
+		}
+	}
+}
+
+struct HandoffFuture(Arc<ThreadBound<Handoff>>);
+
+impl Future for HandoffFuture {
+	type Output = ();
+
+	fn poll(self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+		let mut future = {
+			let mut state = self.0.state.borrow_mut();
+			match std::mem::replace(&mut *state, HandoffState::Done) {
+				HandoffState::Waiting(_) => {
+					*state = HandoffState::Waiting(Some(ctx.waker().clone()));
+					return Poll::Pending
+				},
+				HandoffState::Running(future) => future,
+				HandoffState::Done => return Poll::Ready(())
+			}
+		};
+
+		// The future is polled outside of the lock, since it may process other events.
+		match future.as_mut().poll(ctx) {
+			Poll::Ready(()) => Poll::Ready(()),
+			Poll::Pending => {
+				*self.0.state.borrow_mut() = HandoffState::Running(future);
+				Poll::Pending
+			}
+		}
 	}
 }
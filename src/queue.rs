@@ -1,27 +1,88 @@
 use std::sync::Arc;
+use std::cell::Cell;
+use std::marker::PhantomData;
+use std::mem::ManuallyDrop;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::future::Future as StdFuture;
 use std::task::{Waker, Context, Poll};
 use std::pin::Pin;
+use std::time::Duration;
 use crossbeam_queue::SegQueue as AtomicQueue;
-use parking_lot::Mutex;
-use crate::{Event, Remote, Handler, Pending, Future, ToReceive, Initialize};
+use parking_lot::{Mutex, RwLock};
+use crate::{Event, Remote, Handler, Pending, Future, Ask, ToReceive, ToNotify, Initialize, SendError, Priority, Capacity, Overflow};
+use crate::mailbox::{Mailbox, Acquire};
+use crate::blocking;
+use crate::timer::{Timers, Timer, Sleep, Action, Clock};
+
+/// Number of priority levels.
+const LEVELS: usize = 3;
 
 pub struct Queue<T> {
-	inner: AtomicQueue<T>,
-	waker: Mutex<Option<Waker>>
+	/// One queue per priority level.
+	inner: [AtomicQueue<T>; LEVELS],
+	waker: Mutex<Option<Waker>>,
+	closed: AtomicBool,
+
+	/// Set once the consumer is gone.
+	///
+	/// Pushers hold a read lock, so that no value is pushed once the queue is terminated.
+	terminated: RwLock<bool>,
+
+	/// Counts the events sent to the actors of the queue that are not being handled yet.
+	mailbox: Mailbox,
+
+	timers: Timers
 }
 
 impl<T> Queue<T> {
-	pub fn new() -> Queue<T> {
+	pub fn new(capacity: Option<Capacity>) -> Queue<T> {
 		Queue {
-			inner: AtomicQueue::new(),
-			waker: Mutex::new(None)
+			inner: [AtomicQueue::new(), AtomicQueue::new(), AtomicQueue::new()],
+			waker: Mutex::new(None),
+			closed: AtomicBool::new(false),
+			terminated: RwLock::new(false),
+			mailbox: Mailbox::new(capacity),
+			timers: Timers::new()
+		}
+	}
+
+	/// Push a value, unless the queue is terminated, in which case the value is given back.
+	pub fn push(&self, value: T, priority: Priority) -> Result<(), T> {
+		{
+			let terminated = self.terminated.read();
+			if *terminated {
+				return Err(value)
+			}
+
+			self.inner[priority as usize].push(value);
 		}
+
+		self.wake();
+		Ok(())
+	}
+
+	/// Mark the queue as terminated: its consumer is gone.
+	///
+	/// No value is pushed once this returns: the remaining values can be drained for good.
+	pub fn terminate(&self) {
+		*self.terminated.write() = true
+	}
+
+	pub fn is_terminated(&self) -> bool {
+		*self.terminated.read()
+	}
+
+	/// Mark the queue as closed and wake up its consumer.
+	pub fn close(&self) {
+		self.closed.store(true, Ordering::SeqCst);
+		self.wake()
 	}
 
-	pub fn push(&self, value: T) {
-		self.inner.push(value);
+	pub fn is_closed(&self) -> bool {
+		self.closed.load(Ordering::SeqCst)
+	}
 
+	fn wake(&self) {
 		let mut waker = None;
 		if let Some(mut locked_waker) = self.waker.try_lock() {
 			std::mem::swap(&mut waker, &mut locked_waker);
@@ -32,26 +93,17 @@ impl<T> Queue<T> {
 		}
 	}
 
-	pub fn pop(&self, new_waker: Waker) -> Option<T> {
-		{
-			// We update the waker *before* the pop so that it is available to any push.
-			let mut waker = self.waker.lock();
-			*waker = Some(new_waker);
-		}
-
-		if let Ok(value) = self.inner.pop() {
-			let mut waker = None;
-			std::mem::swap(&mut waker, &mut self.waker.lock());
-			if let Some(waker) = waker {
-				waker.wake();
-			}
+	/// Register the waker to call when a value is pushed or the queue is closed.
+	///
+	/// The waker is consumed by the first push following its registration.
+	pub fn register(&self, new_waker: &Waker) {
+		let mut waker = self.waker.lock();
+		*waker = Some(new_waker.clone());
+	}
 
-			Some(value)
-		} else {
-			// We don't need to call a waker here.
-			// If a push occured after this pop, the waker was available and waked.
-			None
-		}
+	/// Pop the oldest value of the highest priority.
+	pub fn pop(&self) -> Option<T> {
+		self.inner.iter().rev().find_map(|queue| queue.pop().ok())
 	}
 }
 
@@ -63,15 +115,187 @@ pub struct EventQueueRef {
 
 impl EventQueueRef {
 	/// Push an event to the queue.
+	///
+	/// If the receiver is stopped, the event is rejected and polling the returned future panics.
+	/// If the receiver's mailbox or the queue mailbox is full, the overflow policy applies: see
+	/// [`Overflow`].
+	/// The event is scheduled with its default priority, [`Event::PRIORITY`].
 	pub fn push<E: 'static + Event, T: 'static + ?Sized + Handler<E>>(&self, receiver: Remote<T>, event: E) -> Future<T, E::Response> {
-		let pending = Box::new(ToReceive::new(receiver, event));
-		let future = Future::new(pending.state().clone());
-		self.queue.push(pending);
-		future
+		self.push_with_priority(receiver, event, E::PRIORITY)
+	}
+
+	/// Push an event to the queue with the given priority.
+	///
+	/// See [`EventQueueRef::push`].
+	pub fn push_with_priority<E: 'static + Event, T: 'static + ?Sized + Handler<E>>(&self, receiver: Remote<T>, event: E, priority: Priority) -> Future<T, E::Response> {
+		Future::new(self.request(receiver, event, priority))
+	}
+
+	/// Push an event to the queue, with a response future resolving into an error if the event
+	/// cannot be handled.
+	///
+	/// See [`EventQueueRef::push`] and [`Remote::ask`].
+	pub(crate) fn request<E: 'static + Event, T: 'static + ?Sized + Handler<E>>(&self, receiver: Remote<T>, event: E, priority: Priority) -> Ask<T, E::Response> {
+		let is_stopped = receiver.is_stopped();
+		let pending = Box::new(ToReceive::new(receiver, event, priority));
+		let state = pending.state().clone();
+
+		if is_stopped {
+			pending.discard(SendError::ActorStopped);
+			Ask::new(state, None)
+		} else {
+			Ask::new(state, self.deliver(pending, None))
+		}
+	}
+
+	/// Push an event to the queue, without waiting for its response.
+	///
+	/// The event is dropped if the receiver is stopped, or if a mailbox is full.
+	/// See [`Remote::tell`].
+	pub fn notify<E: 'static + Event, T: 'static + ?Sized + Handler<E>>(&self, receiver: Remote<T>, event: E, priority: Priority) {
+		if !receiver.is_stopped() {
+			let mut pending = ToNotify::new(receiver, event, priority);
+			if pending.try_reserve() == Acquire::Acquired {
+				self.push_any(Box::new(pending))
+			}
+		}
+	}
+
+	/// Push an event to the queue without waiting for its response, ignoring the capacity of the
+	/// mailboxes.
+	///
+	/// The event is dropped if the receiver is stopped. Used for notifications that must not be
+	/// lost, like the failures of supervised children.
+	pub(crate) fn notify_unbounded<E: 'static + Event, T: 'static + ?Sized + Handler<E>>(&self, receiver: Remote<T>, event: E, priority: Priority) {
+		if !receiver.is_stopped() {
+			self.push_any(Box::new(ToNotify::new(receiver, event, priority)))
+		}
+	}
+
+	/// Push an event to the queue, failing immediately if a mailbox is full.
+	///
+	/// Returns [`SendError::Full`] if the event would have to wait for some room, or if it is
+	/// rejected by the [`Overflow::DropNewest`] policy.
+	pub(crate) fn try_push<E: 'static + Event, T: 'static + ?Sized + Handler<E>>(&self, receiver: Remote<T>, event: E, priority: Priority) -> Result<Ask<T, E::Response>, SendError> {
+		let pending = Box::new(ToReceive::new(receiver, event, priority));
+		let future = Ask::new(pending.state().clone(), None);
+
+		match pending.reserve(None) {
+			Acquire::Acquired => {
+				self.push_any(pending);
+				Ok(future)
+			},
+			Acquire::Full(_) => Err(SendError::Full)
+		}
+	}
+
+	/// Reserve some room for the pending event in the mailboxes and push it.
+	///
+	/// Returns the pending event back if it must wait for some room, in which case the given
+	/// waker, if any, is called once some room is made.
+	pub(crate) fn deliver(&self, pending: Box<dyn Pending>, waker: Option<&Waker>) -> Option<Box<dyn Pending>> {
+		match pending.reserve(waker) {
+			Acquire::Acquired => self.push_any(pending),
+			Acquire::Full(Overflow::Wait) => return Some(pending),
+			Acquire::Full(_) => pending.discard(SendError::Full)
+		}
+
+		None
+	}
+
+	pub(crate) fn request_initialization<T: 'static, F: 'static + Send + FnOnce() -> T>(&self, remote: Remote<T>, constructor: F) {
+		self.push_any(Box::new(Initialize::new(remote, constructor)));
+	}
+
+	/// Push a pending event to the queue.
+	///
+	/// The event is discarded if the processor has terminated. Events pushed before are
+	/// discarded by the processor itself, in its thread.
+	pub(crate) fn push_any(&self, pending: Box<dyn Pending>) {
+		let priority = pending.priority();
+		if let Err(pending) = self.queue.push(pending, priority) {
+			pending.discard(SendError::QueueClosed)
+		}
+	}
+
+	pub(crate) fn mailbox(&self) -> &Mailbox {
+		&self.queue.mailbox
+	}
+
+	/// Number of events sent to the actors of the queue that are not being handled yet.
+	pub(crate) fn load(&self) -> usize {
+		self.queue.mailbox.len()
+	}
+
+	/// Capacity of the queue mailbox, shared by every actor of the queue.
+	///
+	/// The queue is unbounded if `None`.
+	pub fn capacity(&self) -> Option<Capacity> {
+		self.queue.mailbox.capacity()
+	}
+
+	/// Set the capacity of the queue mailbox.
+	///
+	/// Events already in the mailbox are not affected.
+	pub fn set_capacity(&self, capacity: Option<Capacity>) {
+		self.queue.mailbox.set_capacity(capacity)
+	}
+
+	pub(crate) fn timers(&self) -> &Timers {
+		&self.queue.timers
+	}
+
+	/// Schedule an action, run by the queue processor once the given delay has elapsed.
+	///
+	/// The action is dropped if the processor terminates before.
+	pub(crate) fn schedule(&self, delay: Duration, action: Action) -> Timer {
+		let id = self.queue.timers.schedule(delay, action);
+		if self.queue.is_terminated() {
+			self.queue.timers.clear()
+		}
+
+		Timer::new(self.clone(), id)
+	}
+
+	/// Wait for the given duration.
+	///
+	/// The returned future is woken up by the queue processor, and can be awaited in the
+	/// handlers of the queue actors.
+	pub fn sleep(&self, duration: Duration) -> Sleep {
+		Sleep::new(self.clone(), duration)
+	}
+
+	/// Clock of the queue timers.
+	pub fn clock(&self) -> Arc<dyn Clock> {
+		self.queue.timers.clock()
+	}
+
+	/// Set the clock of the queue timers.
+	///
+	/// This should be done before any timer is scheduled: timers already scheduled keep their
+	/// deadline.
+	pub fn set_clock(&self, clock: Arc<dyn Clock>) {
+		self.queue.timers.set_clock(clock)
+	}
+
+	/// Checks if the queue processor has terminated, or has been dropped.
+	pub(crate) fn is_terminated(&self) -> bool {
+		self.queue.is_terminated()
+	}
+
+	/// Request the queue processor to shut down.
+	///
+	/// The processor stops waiting for new events once every event already in the queue has been
+	/// posted, and every pending asynchronous handler has completed.
+	/// Events sent while the processor is draining the queue are still processed, so that
+	/// actors awaiting each other can finish their work.
+	pub fn close(&self) {
+		self.queue.close()
 	}
 
-	pub(crate) unsafe fn request_initialization<T: 'static, F: 'static>(&self, remote: Remote<T>, constructor: F) where F: Send + FnOnce() -> T {
-		self.queue.push(Box::new(Initialize::new(remote, constructor)));
+	/// Checks if the queue has been closed with [`EventQueueRef::close`].
+	pub fn is_closed(&self) -> bool {
+		self.queue.is_closed()
 	}
 }
 
@@ -83,17 +307,35 @@ impl PartialEq for EventQueueRef {
 
 impl Eq for EventQueueRef {}
 
+/// An event queue, not processed yet.
+///
+/// If the queue is dropped without being processed, every event sent to its actors is rejected
+/// with [`SendError::QueueClosed`].
 pub struct EventQueue {
-	queue: Arc<Queue<Box<dyn Pending>>>
+	queue: Arc<Queue<Box<dyn Pending>>>,
+
+	// Not `Sync`.
+	marker: PhantomData<Cell<()>>
 }
 
-impl !Sync for EventQueue {}
 assert_not_impl_any!(EventQueue: Sync);
 
 impl EventQueue {
 	pub fn new() -> EventQueue {
 		EventQueue {
-			queue: Arc::new(Queue::new())
+			queue: Arc::new(Queue::new(None)),
+			marker: PhantomData
+		}
+	}
+
+	/// Create a new event queue with a bounded mailbox.
+	///
+	/// The capacity limits the number of events sent to the actors of the queue that are not
+	/// being handled yet. See [`EventQueueRef::set_capacity`].
+	pub fn with_capacity(capacity: Capacity) -> EventQueue {
+		EventQueue {
+			queue: Arc::new(Queue::new(Some(capacity))),
+			marker: PhantomData
 		}
 	}
 
@@ -104,11 +346,65 @@ impl EventQueue {
 	}
 
 	pub fn process(self) -> EventQueueProcessor {
+		// The queue is handed over to the processor: it must not be terminated on drop.
+		let this = ManuallyDrop::new(self);
+		let queue = unsafe {
+			std::ptr::read(&this.queue)
+		};
+
 		EventQueueProcessor {
-			queue: self.queue,
-			pending_futures: Vec::new()
+			queue,
+			pending_futures: Vec::new(),
+			budget: DEFAULT_BUDGET,
+			summary: Summary::default()
 		}
 	}
+
+	/// Process the queue in the current thread, until the processor terminates.
+	///
+	/// This uses a minimal built-in executor that parks the thread while there is nothing to
+	/// process. See [`EventQueueProcessor`] for more details.
+	pub fn run_blocking(self) -> Summary {
+		blocking::block_on(self.process())
+	}
+
+	/// Process the queue in a task of the current tokio [`LocalSet`](tokio::task::LocalSet).
+	///
+	/// # Panics
+	/// Panics if called outside of a `LocalSet`.
+	#[cfg(feature = "tokio")]
+	pub fn spawn_tokio(self) -> tokio::task::JoinHandle<Summary> {
+		tokio::task::spawn_local(self.process())
+	}
+
+	/// Process the queue in a task of the current async-std thread.
+	#[cfg(feature = "async-std")]
+	pub fn spawn_async_std(self) -> async_std::task::JoinHandle<Summary> {
+		async_std::task::spawn_local(self.process())
+	}
+}
+
+impl Drop for EventQueue {
+	fn drop(&mut self) {
+		// The queue is never processed.
+		terminate(&self.queue)
+	}
+}
+
+impl Default for EventQueue {
+	fn default() -> EventQueue {
+		EventQueue::new()
+	}
+}
+
+/// Default maximum number of events posted by an [`EventQueueProcessor`] per poll.
+pub const DEFAULT_BUDGET: usize = 128;
+
+/// Summary of the work done by an [`EventQueueProcessor`].
+#[derive(Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct Summary {
+	/// Number of events posted to the actors, including initialization requests.
+	pub events: usize
 }
 
 /// Event Queue Processor.
@@ -116,15 +412,39 @@ impl EventQueue {
 /// This is the object in charge of processin a queue and actually posting the events to the
 /// actors.
 ///
+/// # Budget
+/// Each time it is polled, the processor posts every event available in the queue, up to its
+/// budget (see [`EventQueueProcessor::set_budget`]).
+/// Events are posted in the order they have been pushed.
+/// Once the budget is exhausted, the processor yields back to the executor and immediately
+/// requests to be polled again, so that other tasks of the same thread are not starved.
+///
+/// # Shutdown
+/// The processor runs until its queue is closed with [`EventQueueRef::close`].
+/// It then drains the queue, waits for every pending asynchronous handler to complete, and
+/// resolves with a [`Summary`] of its work.
+///
+/// Once the processor has terminated, or if it is dropped before, every event sent to an actor of
+/// its queue is rejected with [`SendError::QueueClosed`].
+///
+/// # Timers
+/// The processor also fires the timers of its queue, such as [`Remote::send_after`] and
+/// [`EventQueueRef::sleep`], using the queue [`Clock`].
+/// Pending timers do not prevent the processor from terminating: they are cancelled.
+///
 /// # Thread Safety
 /// Since every actor attached to the processor's queue must be run in the same thread and never
 /// move (which is the basis of the actor model), this type does not implement `Send` nor `Sync`.
 pub struct EventQueueProcessor {
 	queue: Arc<Queue<Box<dyn Pending>>>,
-	pending_futures: Vec<Pin<Box<dyn StdFuture<Output = ()>>>>
+	pending_futures: Vec<Pin<Box<dyn StdFuture<Output = ()>>>>,
+	budget: usize,
+	summary: Summary
 }
 
+#[cfg(feature = "nightly")]
 impl !Send for EventQueueProcessor {}
+#[cfg(feature = "nightly")]
 impl !Sync for EventQueueProcessor {}
 assert_not_impl_any!(EventQueueProcessor: Send, Sync);
 
@@ -134,31 +454,97 @@ impl EventQueueProcessor {
 			queue: self.queue.clone()
 		}
 	}
+
+	/// Maximum number of events posted per poll.
+	pub fn budget(&self) -> usize {
+		self.budget
+	}
+
+	/// Reject every remaining and future event of the queue.
+	fn terminate(&self) {
+		terminate(&self.queue)
+	}
+
+	/// Set the maximum number of events posted per poll.
+	///
+	/// Use `usize::MAX` to post every available event at once.
+	///
+	/// # Panics
+	/// Panics if `budget` is 0.
+	pub fn set_budget(&mut self, budget: usize) {
+		assert!(budget > 0, "event queue processor budget must be positive");
+		self.budget = budget
+	}
 }
 
 impl futures::future::Future for EventQueueProcessor {
-	type Output = ();
+	type Output = Summary;
 
-	fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
-		retain_mut(&mut self.pending_futures, |future| {
-			match future.as_mut().poll(ctx) {
-				Poll::Pending => true,
-				_ => false
-			}
+	fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<Summary> {
+		let this = &mut *self;
+
+		retain_mut(&mut this.pending_futures, |future| {
+			future.as_mut().poll(ctx).is_pending()
 		});
 
-		if let Some(pending) = self.queue.pop(ctx.waker().clone()) {
-			if let Some(mut future) = pending.post() {
-				match future.as_mut().poll(ctx) {
-					Poll::Pending => {
-						self.pending_futures.push(future);
-					},
-					_ => ()
-				}
+		// The expired timers may push events.
+		this.queue.timers.fire(ctx.waker());
+
+		// We update the waker *before* the pops so that it is available to any push.
+		this.queue.register(ctx.waker());
+
+		// Checked *before* the pops, so that every event pushed before the closing is popped.
+		let closed = this.queue.is_closed();
+
+		let mut budget = this.budget;
+		while budget > 0 {
+			match this.queue.pop() {
+				Some(pending) => {
+					budget -= 1;
+					this.summary.events += 1;
+					if let Some(mut future) = pending.post() {
+						if future.as_mut().poll(ctx).is_pending() {
+							this.pending_futures.push(future);
+						}
+					}
+				},
+				None => break
 			}
 		}
 
-		Poll::Pending
+		if budget == 0 {
+			// There may be more events to process, but we let the other tasks run first.
+			ctx.waker().wake_by_ref();
+			Poll::Pending
+		} else if closed && this.pending_futures.is_empty() {
+			this.terminate();
+			Poll::Ready(this.summary)
+		} else {
+			// We don't need to call a waker here.
+			// If a push occured after the last pop, the waker was available and waked.
+			Poll::Pending
+		}
+	}
+}
+
+impl Drop for EventQueueProcessor {
+	fn drop(&mut self) {
+		self.terminate()
+	}
+}
+
+/// Reject every remaining and future event of the queue.
+///
+/// Must be called by the owner of the queue, in its thread.
+fn terminate(queue: &Queue<Box<dyn Pending>>) {
+	queue.terminate();
+	queue.timers.clear();
+	discard_all(queue)
+}
+
+fn discard_all(queue: &Queue<Box<dyn Pending>>) {
+	while let Some(pending) = queue.pop() {
+		pending.discard(SendError::QueueClosed)
 	}
 }
 
@@ -1,10 +1,16 @@
-use std::marker::Unsize;
+use std::marker::{PhantomData, Unsize};
 use std::ops::{Deref, DerefMut, DispatchFromDyn, CoerceUnsized};
+use std::mem::ManuallyDrop;
 use std::sync::Arc;
 use crate::{Inner, Remote, Local, ThreadLocal, EventQueueRef};
 
+/// Reference to an actor, given to its handlers.
+///
+/// It dereferences to the actor state, and gives access to the actor pointers.
 pub struct Receiver<'a, T: ?Sized> {
-	value: &'a mut T
+	/// Pointer to the actor, obtained from its `Arc`.
+	inner: *const Inner<T>,
+	marker: PhantomData<&'a mut T>
 }
 
 impl<'a, T: ?Sized + Unsize<U>, U: ?Sized> CoerceUnsized<Receiver<'a, U>> for Receiver<'a, T> {}
@@ -14,88 +20,72 @@ impl<'a, T: ?Sized> Deref for Receiver<'a, T> {
 	type Target = T;
 
 	fn deref(&self) -> &T {
-		self.value
+		unsafe {
+			&*self.inner().data_ptr()
+		}
 	}
 }
 
 impl<'a, T: ?Sized> DerefMut for Receiver<'a, T> {
 	fn deref_mut(&mut self) -> &mut T {
-		self.value
+		unsafe {
+			self.inner().state().0
+		}
 	}
 }
 
 impl<'a, T: ?Sized> Receiver<'a, T> {
-	pub(crate) fn new(value: &'a mut T) -> Receiver<'a, T> {
+	/// Create a receiver from a pointer given by `Arc::as_ptr`.
+	///
+	/// The actor must be busy handling an event in the current thread, and stay alive for `'a`.
+	pub(crate) unsafe fn new(inner: *const Inner<T>) -> Receiver<'a, T> {
 		Receiver {
-			value
+			inner,
+			marker: PhantomData
 		}
 	}
 
-	fn inner(&self) -> *const Inner<T> {
+	fn inner(&self) -> &Inner<T> {
 		unsafe {
-			let ptr = self.value as *const T;
-
-			// Find the wrapping remote's inner pointer.
-			let fake_inner_ptr = ptr as *const Inner<T>;
-			let inner_ptr = set_data_ptr(fake_inner_ptr, (ptr as *const u8).offset(-inner_data_offset(ptr)));
+			&*self.inner
+		}
+	}
 
-			inner_ptr
+	/// Get a new `Arc` to the actor.
+	fn arc(&self) -> Arc<Inner<T>> {
+		unsafe {
+			// The pointer comes from `Arc::as_ptr` and the actor is alive, so the `Arc` is valid.
+			// It is not dropped since it does not own a reference count.
+			let arc = ManuallyDrop::new(Arc::from_raw(self.inner));
+			Arc::clone(&arc)
 		}
 	}
 
 	pub fn as_local(&self) -> Local<T> {
-		unsafe {
-			// Reconstruct a wrapping Arc.
-			let fake_arc = Arc::from_raw(self.inner());
-			let local = Local::from_inner(fake_arc.clone()); // create an actual new Arc.
-			std::mem::forget(fake_arc); // forget the fake arc.
+		Local::from_inner(self.arc())
+	}
 
-			// We have now a fully functional Local.
-			local
-		}
+	/// Stop the actor.
+	///
+	/// See [`Remote::stop`] for more details.
+	pub fn stop(&self) {
+		self.inner().stop()
 	}
 
-	pub fn as_remote(&self) -> Remote<T> {
-		unsafe {
-			// Reconstruct a wrapping Arc.
-			let fake_arc = Arc::from_raw(self.inner());
-			let remote = Remote::from_inner(fake_arc.clone()); // create an actual new Arc.
-			std::mem::forget(fake_arc); // forget the fake arc.
+	/// Checks if the event being handled has been cancelled.
+	///
+	/// See [`Context::is_cancelled`](crate::Context::is_cancelled).
+	pub fn is_cancelled(&self) -> bool {
+		self.inner().is_cancelled()
+	}
 
-			// We have now a fully functional Remote.
-			remote
-		}
+	pub fn as_remote(&self) -> Remote<T> {
+		Remote::from_inner(self.arc())
 	}
 }
 
 unsafe impl<'a, T: ?Sized> ThreadLocal for Receiver<'a, T> {
 	fn queue(&self) -> &EventQueueRef {
-		unsafe {
-			&(&*self.inner()).queue
-		}
+		&self.inner().queue
 	}
 }
-
-/// Sets the data pointer of a `?Sized` raw pointer.
-///
-/// For a slice/trait object, this sets the `data` field and leaves the rest
-/// unchanged. For a sized raw pointer, this simply sets the pointer.
-unsafe fn set_data_ptr<T: ?Sized, U>(mut ptr: *const T, data: *const U) -> *const T {
-    std::ptr::write(&mut ptr as *mut _ as *mut *const u8, data as *const u8);
-    ptr
-}
-
-/// Computes the offset of the data field within `Inner`.
-unsafe fn inner_data_offset<T: ?Sized>(ptr: *const T) -> isize {
-	// Align the unsized value to the end of the `Inner`.
-	// Because it is `?Sized`, it will always be the last field in memory.
-	// Note: This is a detail of the current implementation of the compiler,
-	// and is not a guaranteed language detail.
-	inner_data_offset_align(std::mem::align_of_val(&*ptr))
-}
-
-#[inline]
-fn inner_data_offset_align(align: usize) -> isize {
-    let layout = std::alloc::Layout::new::<Inner<()>>();
-    (layout.size() + layout.padding_needed_for(align)) as isize
-}
@@ -0,0 +1,205 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use parking_lot::Mutex;
+use crate::{Event, Handler, Remote, WeakRemote, AlreadyRegistered};
+
+/// Registered actor, type erased.
+trait Entry: Send + Sync {
+	fn is_stopped(&self) -> bool;
+
+	fn as_any(&self) -> &dyn Any;
+}
+
+impl<T: 'static + ?Sized> Entry for WeakRemote<T> {
+	fn is_stopped(&self) -> bool {
+		WeakRemote::is_stopped(self)
+	}
+
+	fn as_any(&self) -> &dyn Any {
+		self
+	}
+}
+
+/// Registered actors of a name, by event type.
+type Entries = HashMap<TypeId, Box<dyn Entry>>;
+
+/// A directory of actors, looked up by name or by event type.
+///
+/// Actors are registered under a name as handlers of a given event type, and looked up as
+/// `Remote<dyn Handler<E>>` pointers. An actor can be registered under the same name for
+/// different event types.
+///
+/// Actors can also announce that they handle a given event type, without any name, so that
+/// they can be discovered with [`Registry::handlers`]: plugins can announce themselves without a
+/// central wiring function. Names and announcements are independent.
+///
+/// The registry does not keep the actors alive: an actor is deregistered automatically once it is
+/// stopped, or dropped.
+/// A process-wide registry is given by [`Registry::global`], but registries can also be created
+/// for a subsystem or a [`Runtime`](crate::Runtime).
+pub struct Registry {
+	/// Registered actors, by name and event type.
+	entries: Mutex<HashMap<String, Entries>>,
+
+	/// Announced actors, by event type, in announcement order.
+	handlers: Mutex<HashMap<TypeId, Vec<Box<dyn Entry>>>>
+}
+
+impl Registry {
+	pub fn new() -> Registry {
+		Registry {
+			entries: Mutex::new(HashMap::new()),
+			handlers: Mutex::new(HashMap::new())
+		}
+	}
+
+	/// Process-wide registry.
+	pub fn global() -> &'static Registry {
+		static GLOBAL: OnceLock<Registry> = OnceLock::new();
+		GLOBAL.get_or_init(Registry::new)
+	}
+
+	/// Register the actor under the given name, as a handler of `E` events.
+	///
+	/// Fails if another actor is already registered under this name for `E` events, unless it
+	/// is stopped. Registering the same actor again has no effect.
+	pub fn register<E, T>(&self, name: impl Into<String>, remote: &Remote<T>) -> Result<(), AlreadyRegistered> where E: 'static + Event, T: 'static + Handler<E> {
+		let remote: Remote<dyn Handler<E>> = crate::upcast!(remote.clone() => dyn Handler<E>);
+		let mut entries = self.entries.lock();
+
+		// Drop the stopped actors on the way.
+		entries.retain(|_, entries| {
+			entries.retain(|_, entry| !entry.is_stopped());
+			!entries.is_empty()
+		});
+
+		let entries = entries.entry(name.into()).or_default();
+		if let Some(entry) = entries.get(&TypeId::of::<E>()) {
+			let registered = entry.as_any().downcast_ref::<WeakRemote<dyn Handler<E>>>().unwrap();
+			return if registered.upgrade().as_ref() == Some(&remote) {
+				Ok(())
+			} else {
+				Err(AlreadyRegistered)
+			}
+		}
+
+		entries.insert(TypeId::of::<E>(), Box::new(remote.downgrade()));
+		Ok(())
+	}
+
+	/// Remove the actor registered under the given name for `E` events.
+	///
+	/// Returns `true` if an actor was registered, and is not stopped.
+	pub fn unregister<E: 'static + Event>(&self, name: &str) -> bool {
+		let mut entries = self.entries.lock();
+		let entry = match entries.get_mut(name) {
+			Some(by_type) => {
+				let entry = by_type.remove(&TypeId::of::<E>());
+				if by_type.is_empty() {
+					entries.remove(name);
+				}
+
+				entry
+			},
+			None => None
+		};
+
+		entry.is_some_and(|entry| !entry.is_stopped())
+	}
+
+	/// Get the actor registered under the given name for `E` events.
+	pub fn lookup<E: 'static + Event>(&self, name: &str) -> Option<Remote<dyn Handler<E>>> {
+		let mut entries = self.entries.lock();
+		let by_type = entries.get_mut(name)?;
+		let entry = by_type.get(&TypeId::of::<E>())?;
+		match entry.as_any().downcast_ref::<WeakRemote<dyn Handler<E>>>().unwrap().upgrade() {
+			Some(remote) => Some(remote),
+			None => {
+				by_type.remove(&TypeId::of::<E>());
+				if by_type.is_empty() {
+					entries.remove(name);
+				}
+
+				None
+			}
+		}
+	}
+
+	/// Names under which actors are registered, for any event type.
+	pub fn names(&self) -> Vec<String> {
+		let entries = self.entries.lock();
+		entries.iter().filter(|(_, by_type)| by_type.values().any(|entry| !entry.is_stopped())).map(|(name, _)| name.clone()).collect()
+	}
+
+	/// Announce that the actor handles `E` events.
+	///
+	/// Announcing the same actor again has no effect.
+	pub fn announce<E, T>(&self, remote: &Remote<T>) where E: 'static + Event, T: 'static + Handler<E> {
+		let remote: Remote<dyn Handler<E>> = crate::upcast!(remote.clone() => dyn Handler<E>);
+		let mut handlers = self.handlers.lock();
+		let handlers = handlers.entry(TypeId::of::<E>()).or_default();
+		handlers.retain(|entry| !entry.is_stopped());
+
+		let is_announced = handlers.iter().any(|entry| {
+			entry.as_any().downcast_ref::<WeakRemote<dyn Handler<E>>>().unwrap().upgrade().as_ref() == Some(&remote)
+		});
+
+		if !is_announced {
+			handlers.push(Box::new(remote.downgrade()))
+		}
+	}
+
+	/// Withdraw the announcement of the actor for `E` events.
+	///
+	/// Returns `true` if the actor was announced.
+	pub fn withdraw<E, T>(&self, remote: &Remote<T>) -> bool where E: 'static + Event, T: 'static + Handler<E> {
+		let mut handlers = self.handlers.lock();
+		let handlers = match handlers.get_mut(&TypeId::of::<E>()) {
+			Some(handlers) => handlers,
+			None => return false
+		};
+
+		let mut is_announced = false;
+		handlers.retain(|entry| {
+			match entry.as_any().downcast_ref::<WeakRemote<dyn Handler<E>>>().unwrap().upgrade() {
+				Some(entry) if entry.id() == remote.id() => {
+					is_announced = true;
+					false
+				},
+				Some(_) => true,
+				None => false
+			}
+		});
+
+		is_announced
+	}
+
+	/// Every actor announced as a handler of `E` events and not stopped, in announcement order.
+	pub fn handlers<E: 'static + Event>(&self) -> Vec<Remote<dyn Handler<E>>> {
+		let mut handlers = self.handlers.lock();
+		let handlers = match handlers.get_mut(&TypeId::of::<E>()) {
+			Some(handlers) => handlers,
+			None => return Vec::new()
+		};
+
+		let mut remotes = Vec::with_capacity(handlers.len());
+		handlers.retain(|entry| {
+			match entry.as_any().downcast_ref::<WeakRemote<dyn Handler<E>>>().unwrap().upgrade() {
+				Some(remote) => {
+					remotes.push(remote);
+					true
+				},
+				None => false
+			}
+		});
+
+		remotes
+	}
+}
+
+impl Default for Registry {
+	fn default() -> Registry {
+		Registry::new()
+	}
+}
@@ -1,49 +1,568 @@
+#[cfg(feature = "nightly")]
 use std::marker::Unsize;
+#[cfg(feature = "nightly")]
 use std::ops::{DispatchFromDyn, CoerceUnsized};
 use std::sync::{Arc, Weak};
-use std::cell::RefCell;
-use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicUsize, AtomicU64, AtomicBool, Ordering};
+use std::cell::{RefCell, UnsafeCell};
+use std::mem::ManuallyDrop;
+use std::ptr::addr_of_mut;
 use std::hash::{Hash, Hasher};
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
 use std::collections::VecDeque;
+use std::time::Duration;
+use parking_lot::Mutex;
+#[cfg(feature = "nightly")]
+use crate::Receiver;
 use crate::{
 	Output,
-	Receiver,
+	Context,
+	AnyContext,
 	Event,
 	EventQueueRef,
 	Handler,
 	Future,
+	Ask,
 	future::LocalFuture,
 	Local,
 	ThreadLocal,
+	SendError,
+	Priority,
+	Capacity,
+	mailbox::Mailbox,
+	Timer,
+	timer::Action,
 	Emitter,
 	SubscriptionEvent,
 	Pending,
-	pending
+	pending,
+	Lifecycle,
+	lifecycle,
+	deadlock
 };
 
-pub struct Actor<T: ?Sized> {
-	// pub(crate) inbox: VecDeque<(Box<dyn Pending>, Arc<Mutex<pending::FutureState>>)>,
+/// Identifier of an actor, unique in the process.
+///
+/// Actor identifiers are given in creation order, and never reused.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct ActorId(u64);
+
+impl ActorId {
+	fn next() -> ActorId {
+		static NEXT: AtomicU64 = AtomicU64::new(0);
+		ActorId(NEXT.fetch_add(1, Ordering::Relaxed))
+	}
+
+	pub fn get(&self) -> u64 {
+		self.0
+	}
+}
+
+impl fmt::Display for ActorId {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "#{}", self.0)
+	}
+}
+
+/// Initialization state of an actor state.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Status {
+	/// The actor state is being constructed in the actor's thread.
+	///
+	/// Events received in the meantime wait in the inbox.
+	Uninit,
+
+	/// The actor state is initialized.
+	Ready,
+
+	/// The actor state has been dropped.
+	Stopped,
+
+	/// The actor state could not be constructed because the constructor panicked.
+	Failed
+}
+
+/// Event handling state of an actor.
+pub(crate) struct Actor {
 	pub(crate) inbox: VecDeque<Box<dyn Pending>>,
 	pub(crate) is_busy: bool,
-	pub(crate) data: T
+
+	/// Set if a reentrant handler panicked while the actor was busy: the actor fails once it is
+	/// no longer busy.
+	pub(crate) is_failing: bool,
+
+	pub(crate) status: Status
 }
 
-impl<T: ?Sized> Actor<T> {
-	/// Must be called from the actor thread.
-	pub(crate) unsafe fn post<E: Event>(&mut self, event: E) -> Output<E::Response> where T: 'static + Handler<E> {
-		let local = Receiver::new(&mut self.data);
-		local.handle(event)
+impl Actor {
+	fn new() -> Actor {
+		Actor {
+			inbox: VecDeque::new(),
+			is_busy: false,
+			is_failing: false,
+			status: Status::Uninit
+		}
+	}
+
+	/// Checks if the actor state is gone, or will never be constructed.
+	pub(crate) fn is_stopped(&self) -> bool {
+		matches!(self.status, Status::Stopped | Status::Failed)
 	}
 
-	pub(crate) unsafe fn init(&mut self, mut value: T) where T: Sized {
-		std::mem::swap(&mut self.data, &mut value);
-		std::mem::forget(value)
+	/// Error of the events discarded because the actor is stopped.
+	fn stop_error(&self) -> SendError {
+		if self.status == Status::Failed {
+			SendError::HandlerPanicked
+		} else {
+			SendError::ActorStopped
+		}
 	}
 }
 
 pub(crate) struct Inner<T: ?Sized> {
 	pub(crate) queue: EventQueueRef, // + 8
-	pub(crate) actor: RefCell<Actor<T>>
+
+	id: ActorId,
+
+	/// Human-readable name of the actor.
+	name: Mutex<Option<String>>,
+
+	/// Type name of the actor state.
+	type_name: &'static str,
+
+	/// Number of `Remote` and `Local` handles to the actor.
+	handles: AtomicUsize,
+
+	/// Set once the actor is stopped, or when the last handle has been dropped.
+	stopping: AtomicBool,
+
+	/// Set if the actor panicked.
+	failed: AtomicBool,
+
+	/// Request pushed to the queue when the actor is stopped.
+	release: Mutex<Option<Box<dyn Pending>>>,
+
+	/// Callbacks called if the actor fails.
+	monitors: Mutex<Vec<Box<dyn FnOnce() + Send>>>,
+
+	/// Counts the events sent to the actor that are not being handled yet.
+	pub(crate) mailbox: Mailbox,
+
+	/// Default time given to the actor to respond to an event.
+	reply_timeout: Mutex<Option<Duration>>,
+
+	pub(crate) actor: RefCell<Actor>,
+
+	/// Cancellation flag of the event being handled.
+	///
+	/// Only accessed in the actor's thread.
+	cancellation: RefCell<Option<Arc<AtomicBool>>>,
+
+	/// Context given to the handlers.
+	///
+	/// Only accessed in the actor's thread, while the actor is busy.
+	context: UnsafeCell<AnyContext>,
+
+	/// Actor state.
+	///
+	/// It is kept out of `actor` so that the event handling state can be borrowed while a handler
+	/// holds a reference to the actor state.
+	/// It is only initialized once the actor status is `Ready`, and only accessed in the actor's
+	/// thread, while the actor is busy.
+	data: UnsafeCell<ManuallyDrop<T>>
+}
+
+impl<T: ?Sized> Inner<T> {
+	fn new(queue: EventQueueRef, data: T) -> Arc<Inner<T>> where T: 'static + Sized {
+		let inner = Inner::uninit(queue);
+		unsafe {
+			// No one else can access the actor yet.
+			inner.init(data)
+		}
+
+		inner
+	}
+
+	/// Allocate a new actor, without initializing its state.
+	fn uninit(queue: EventQueueRef) -> Arc<Inner<T>> where T: 'static + Sized {
+		let mut inner = Arc::<Inner<T>>::new_uninit();
+		let ptr = Arc::get_mut(&mut inner).unwrap().as_mut_ptr();
+
+		let inner = unsafe {
+			// Every field is initialized but the actor state, which is never accessed while the
+			// actor status is `Uninit`.
+			addr_of_mut!((*ptr).id).write(ActorId::next());
+			addr_of_mut!((*ptr).name).write(Mutex::new(None));
+			addr_of_mut!((*ptr).type_name).write(std::any::type_name::<T>());
+			addr_of_mut!((*ptr).handles).write(AtomicUsize::new(0));
+			addr_of_mut!((*ptr).stopping).write(AtomicBool::new(false));
+			addr_of_mut!((*ptr).failed).write(AtomicBool::new(false));
+			addr_of_mut!((*ptr).release).write(Mutex::new(None));
+			addr_of_mut!((*ptr).monitors).write(Mutex::new(Vec::new()));
+			addr_of_mut!((*ptr).mailbox).write(Mailbox::new(None));
+			addr_of_mut!((*ptr).reply_timeout).write(Mutex::new(None));
+			addr_of_mut!((*ptr).actor).write(RefCell::new(Actor::new()));
+			addr_of_mut!((*ptr).cancellation).write(RefCell::new(None));
+			addr_of_mut!((*ptr).context).write(UnsafeCell::new(Context::new(Weak::<Inner<T>>::new(), queue.clone())));
+			addr_of_mut!((*ptr).queue).write(queue);
+			Arc::from_raw(Arc::into_raw(inner) as *const Inner<T>)
+		};
+
+		let this: Weak<Inner<T>> = Arc::downgrade(&inner);
+		unsafe {
+			// The context is not accessed until the actor handles its first event.
+			*inner.context.get() = Context::new(this, inner.queue.clone());
+		}
+
+		inner.set_release(lifecycle::release(&inner));
+		inner
+	}
+
+	/// Call the handler of the actor for the given event.
+	///
+	/// Must be called from the actor thread, while the actor is busy.
+	#[cfg(feature = "nightly")]
+	pub(crate) unsafe fn post<E: Event>(this: &Arc<Self>, event: E) -> Output<'_, E::Response> where T: 'static + Handler<E> {
+		Receiver::new(Arc::as_ptr(this)).handle(event)
+	}
+
+	/// Call the handler of the actor for the given event.
+	///
+	/// Must be called from the actor thread, while the actor is busy.
+	#[cfg(not(feature = "nightly"))]
+	pub(crate) unsafe fn post<E: Event>(this: &Arc<Self>, event: E) -> Output<'_, E::Response> where T: 'static + Handler<E> {
+		let (data, context) = this.state();
+		data.dispatch(context.cast(), event)
+	}
+
+	/// Get the actor state and its context.
+	///
+	/// Must be called from the actor thread, while the actor is busy, and the returned references
+	/// must not outlive the current event handling.
+	#[allow(clippy::mut_from_ref)]
+	pub(crate) unsafe fn state(&self) -> (&mut T, &mut Context<T>) {
+		(&mut **self.data.get(), (*self.context.get()).cast())
+	}
+
+	/// The actor in the deadlock detection graph.
+	pub(crate) fn node(&self) -> deadlock::Node {
+		deadlock::Node {
+			id: self.id,
+			type_name: self.type_name
+		}
+	}
+
+	pub(crate) fn id(&self) -> ActorId {
+		self.id
+	}
+
+	pub(crate) fn name(&self) -> Option<String> {
+		self.name.lock().clone()
+	}
+
+	pub(crate) fn set_name(&self, name: Option<String>) {
+		*self.name.lock() = name
+	}
+
+	/// Pointer to the actor state.
+	pub(crate) fn data_ptr(&self) -> *const T {
+		self.data.get() as *const T
+	}
+
+	/// Initialize the actor state.
+	///
+	/// Must be called from the actor thread, while the actor status is `Uninit`.
+	pub(crate) unsafe fn init(&self, value: T) where T: Sized {
+		std::ptr::write(self.data.get(), ManuallyDrop::new(value));
+		self.actor.borrow_mut().status = Status::Ready
+	}
+
+	/// Initialize the actor state with the given constructor, in the actor's thread.
+	///
+	/// The events received in the meantime are then processed. If the constructor panics, the
+	/// actor fails.
+	pub(crate) fn construct<F: FnOnce() -> T>(&self, constructor: F) where T: Sized {
+		match panic::catch_unwind(AssertUnwindSafe(constructor)) {
+			Ok(value) => unsafe {
+				self.init(value);
+				self.restart()
+			},
+			Err(_) => {
+				self.actor.borrow_mut().status = Status::Failed;
+				self.fail()
+			}
+		}
+	}
+
+	/// Drop the actor state.
+	///
+	/// Must be called from the actor thread, while the actor state is not borrowed.
+	/// Calling this function more than once has no effect.
+	pub(crate) unsafe fn drop_data(&self) {
+		let status = {
+			let mut actor = self.actor.borrow_mut();
+			let status = actor.status;
+			if status != Status::Failed {
+				actor.status = Status::Stopped
+			}
+
+			status
+		};
+
+		if status == Status::Ready {
+			ManuallyDrop::drop(&mut *self.data.get())
+		}
+	}
+
+	/// Set the request to push to the queue when the actor is stopped.
+	pub(crate) fn set_release(&self, request: Box<dyn Pending>) {
+		*self.release.lock() = Some(request)
+	}
+
+	pub(crate) fn acquire(&self) {
+		self.handles.fetch_add(1, Ordering::AcqRel);
+	}
+
+	/// Acquire a new handle, unless the last handle is gone or the actor is stopping.
+	///
+	/// Unlike [`Inner::acquire`], this does not require an existing handle: the count is never
+	/// incremented from zero, since the actor is already stopped then.
+	pub(crate) fn try_acquire(&self) -> bool {
+		let mut handles = self.handles.load(Ordering::Acquire);
+		loop {
+			if handles == 0 || self.is_stopping() {
+				return false
+			}
+
+			match self.handles.compare_exchange_weak(handles, handles + 1, Ordering::AcqRel, Ordering::Acquire) {
+				Ok(_) => return true,
+				Err(current) => handles = current
+			}
+		}
+	}
+
+	pub(crate) fn drop_handle(&self) {
+		if self.handles.fetch_sub(1, Ordering::AcqRel) == 1 {
+			self.stop()
+		}
+	}
+
+	/// Stop the actor.
+	///
+	/// The actor does not accept any new event, and the release request is pushed to the queue.
+	/// Calling this function more than once has no effect.
+	pub(crate) fn stop(&self) {
+		self.stopping.store(true, Ordering::Release);
+		let request = self.release.lock().take();
+		if let Some(request) = request {
+			self.queue.push_any(request)
+		}
+	}
+
+	/// Set the cancellation flag of the event being handled, and return the previous one.
+	pub(crate) fn set_cancellation(&self, cancellation: Option<Arc<AtomicBool>>) -> Option<Arc<AtomicBool>> {
+		self.cancellation.replace(cancellation)
+	}
+
+	/// Checks if the event being handled has been cancelled.
+	///
+	/// Must be called from the actor's thread.
+	pub(crate) fn is_cancelled(&self) -> bool {
+		self.cancellation.borrow().as_ref().is_some_and(|cancellation| cancellation.load(Ordering::Acquire))
+	}
+
+	pub(crate) fn reply_timeout(&self) -> Option<Duration> {
+		*self.reply_timeout.lock()
+	}
+
+	pub(crate) fn is_stopping(&self) -> bool {
+		self.stopping.load(Ordering::Acquire)
+	}
+
+	pub(crate) fn is_failed(&self) -> bool {
+		self.failed.load(Ordering::Acquire)
+	}
+
+	/// Mark the actor as failed after a panic.
+	///
+	/// The actor is stopped, its state is dropped right away without calling any lifecycle
+	/// hook, and its pending events are discarded: they fail with
+	/// [`SendError::HandlerPanicked`] if the constructor panicked, and with
+	/// [`SendError::ActorStopped`] otherwise. The failure monitors are called last.
+	///
+	/// The actor status must be set to `Failed` beforehand if the constructor panicked.
+	/// Must be called from the actor's thread, while the actor state is not borrowed.
+	pub(crate) fn fail(&self) {
+		let monitors = {
+			let mut monitors = self.monitors.lock();
+			self.failed.store(true, Ordering::Release);
+			std::mem::take(&mut *monitors)
+		};
+
+		self.stop();
+
+		let (discarded, error) = {
+			let mut actor = self.actor.borrow_mut();
+			actor.is_busy = false;
+			(std::mem::take(&mut actor.inbox), actor.stop_error())
+		};
+
+		// The state may be inconsistent after the panic, so dropping it may panic too.
+		let _ = panic::catch_unwind(AssertUnwindSafe(|| unsafe {
+			self.drop_data()
+		}));
+
+		for pending in discarded {
+			pending.discard(error)
+		}
+
+		for monitor in monitors {
+			monitor()
+		}
+	}
+
+	/// Mark the actor as failed after a panic in a reentrant handler, or from a handler.
+	///
+	/// The actor state may be borrowed by a handler: if the actor is busy, it fails once it is
+	/// done, see [`Inner::idle`].
+	/// Must be called from the actor's thread.
+	pub(crate) fn fail_when_idle(&self) {
+		let is_busy = {
+			let mut actor = self.actor.borrow_mut();
+			actor.is_failing = actor.is_busy;
+			actor.is_busy
+		};
+
+		if !is_busy {
+			self.fail()
+		}
+	}
+
+	/// Mark the actor as no longer busy, once its handler is done.
+	///
+	/// If the actor has been marked as failing in the meantime, it fails now. Returns `true` if
+	/// it does.
+	/// Must be called from the actor's thread, while the actor state is not borrowed.
+	pub(crate) fn idle(&self) -> bool {
+		let is_failing = {
+			let mut actor = self.actor.borrow_mut();
+			actor.is_busy = false;
+			std::mem::take(&mut actor.is_failing)
+		};
+
+		if is_failing {
+			self.fail()
+		}
+
+		is_failing
+	}
+
+	/// Register a callback to call if the actor fails.
+	///
+	/// The callback is called right away if the actor has already failed, and dropped without
+	/// being called if the actor stops normally.
+	pub(crate) fn on_failure(&self, callback: Box<dyn FnOnce() + Send>) {
+		{
+			let mut monitors = self.monitors.lock();
+			if !self.is_failed() {
+				monitors.push(callback);
+				return
+			}
+		}
+
+		callback()
+	}
+
+	/// Drop the failure callbacks, once the actor has stopped normally.
+	pub(crate) fn clear_monitors(&self) {
+		let monitors = std::mem::take(&mut *self.monitors.lock());
+		std::mem::drop(monitors)
+	}
+
+	/// Checks if an event would be processed right away by `post_any`.
+	pub(crate) fn is_available(&self) -> bool {
+		let actor = self.actor.borrow();
+		actor.status == Status::Ready && !actor.is_busy && actor.inbox.is_empty()
+	}
+
+	/// Process the given pending event, or put it in the inbox if the actor is busy.
+	///
+	/// Events wait in the inbox by order of priority.
+	///
+	/// If the actor state has been dropped, the pending event is discarded.
+	/// Must be called from the actor's thread.
+	pub(crate) fn post_any(&self, pending: Box<dyn Pending>) {
+		let stopped = {
+			let mut actor = self.actor.borrow_mut();
+			let is_waiting = actor.status == Status::Uninit || (actor.status == Status::Ready && (actor.is_busy || !actor.inbox.is_empty()));
+			if is_waiting {
+				// The inbox is ordered from the newest event of the lowest priority to the oldest
+				// event of the highest priority.
+				let priority = pending.priority();
+				let i = actor.inbox.iter().position(|other| other.priority() >= priority).unwrap_or(actor.inbox.len());
+				actor.inbox.insert(i, pending);
+				return
+			}
+
+			actor.is_stopped().then(|| actor.stop_error())
+		};
+
+		if let Some(error) = stopped {
+			pending.discard(error)
+		} else {
+			pending.process()
+		}
+	}
+
+	/// Restart the actor events execution.
+	///
+	/// This must be called from the actor's thread,
+	/// and only when no futures bound to this actor are executing.
+	pub(crate) unsafe fn restart(&self) {
+		if self.idle() {
+			return
+		}
+
+		// process the pending events until the actor becomes busy again.
+		while let Some(pending) = self.next_pending() {
+			pending.process()
+		}
+
+		// discard the remaining events if the actor has been stopped.
+		let (discarded, error) = {
+			let mut actor = self.actor.borrow_mut();
+			if actor.is_stopped() {
+				(std::mem::take(&mut actor.inbox), actor.stop_error())
+			} else {
+				(VecDeque::new(), SendError::ActorStopped)
+			}
+		};
+
+		for pending in discarded {
+			pending.discard(error)
+		}
+	}
+
+	/// Pop the next pending event of the inbox, unless the actor is busy or not ready.
+	fn next_pending(&self) -> Option<Box<dyn Pending>> {
+		let mut actor = self.actor.borrow_mut();
+		if actor.is_busy || actor.status != Status::Ready {
+			None
+		} else {
+			actor.inbox.pop_back()
+		}
+	}
+}
+
+impl<T: ?Sized> Drop for Inner<T> {
+	fn drop(&mut self) {
+		if self.actor.get_mut().status == Status::Ready {
+			unsafe {
+				ManuallyDrop::drop(self.data.get_mut())
+			}
+		}
+	}
 }
 
 /// A pointer to a remote actor.
@@ -51,60 +570,56 @@ pub struct Remote<T: ?Sized> {
 	pub(crate) inner: Arc<Inner<T>> // + 8
 }
 
+#[cfg(feature = "nightly")]
 impl<T: ?Sized + Unsize<U>, U: ?Sized> CoerceUnsized<Remote<U>> for Remote<T> {}
+#[cfg(feature = "nightly")]
 impl<T: ?Sized + Unsize<U>, U: ?Sized> DispatchFromDyn<Remote<U>> for Remote<T> {}
 
 unsafe impl<T: ?Sized> Send for Remote<T> {}
 unsafe impl<T: ?Sized> Sync for Remote<T> {}
 
 impl<T: ?Sized> Remote<T> {
+	/// Create a new actor, constructed in the actor's thread.
+	///
+	/// Events sent to the actor before it is constructed wait in its inbox, and are handled once
+	/// the constructor returns.
+	/// If the constructor panics, the actor fails: see [`Remote::is_failed`]. Every event sent to
+	/// the actor is then rejected.
 	pub fn from<F>(queue: EventQueueRef, constructor: F) -> Remote<T> where T: 'static + Sized, F: 'static + Send + FnOnce() -> T {
-		unsafe {
-			let remote = Remote {
-				inner: Arc::new(Inner {
-					queue: queue,
-					actor: RefCell::new(Actor {
-						inbox: VecDeque::new(),
-						is_busy: false,
-						// Why it is safe.
-						// [1] We know the value won't be touched before initialization: the first
-						// message received by the remote pointer is the initialization request.
-						// This is because messages are processed in order in the queue. That is
-						// why (among other things) posting a message directly to a remote
-						// actor is unsafe.
-						// [2] We know the actor won't be dropped before initialization: the
-						// initialization request message holds a copy of the remote.
-						data: MaybeUninit::uninit().assume_init()
-					})
-				})
-			};
-
-			remote.inner.queue.request_initialization(remote.clone(), constructor);
-			remote
-		}
+		let remote = Remote::from_inner(Inner::uninit(queue));
+		remote.inner.queue.request_initialization(remote.clone(), constructor);
+		remote
 	}
 
-	pub fn new(queue: EventQueueRef, value: T) -> Remote<T> where T: Send + Sized {
-		Remote {
-			inner: Arc::new(Inner {
-				queue: queue,
-				actor: RefCell::new(Actor {
-					inbox: VecDeque::new(),
-					is_busy: false,
-					data: value
-				})
-			})
-		}
+	pub fn new(queue: EventQueueRef, value: T) -> Remote<T> where T: 'static + Send + Sized {
+		Remote::from_inner(Inner::new(queue, value))
+	}
+
+	/// Create a new actor with lifecycle hooks.
+	///
+	/// See [`Lifecycle`] for more details on when the hooks are called.
+	pub fn spawn(queue: EventQueueRef, value: T) -> Remote<T> where T: 'static + Send + Sized + Lifecycle {
+		let remote = Remote::new(queue, value);
+		lifecycle::attach(&remote.inner);
+		remote
+	}
+
+	/// Create a new actor with lifecycle hooks, constructed in the actor's thread.
+	///
+	/// The `started` hook is called right after the actor is constructed.
+	/// See [`Lifecycle`] for more details.
+	pub fn spawn_from<F>(queue: EventQueueRef, constructor: F) -> Remote<T> where T: 'static + Sized + Lifecycle, F: 'static + Send + FnOnce() -> T {
+		let remote = Remote::from(queue, constructor);
+		lifecycle::attach(&remote.inner);
+		remote
 	}
 
 	pub fn as_ptr(&self) -> *const T {
-		let actor_ptr = self.inner.actor.as_ptr();
-		unsafe {
-			&(*actor_ptr).data
-		}
+		self.inner.data_ptr()
 	}
 
 	pub(crate) fn from_inner(inner: Arc<Inner<T>>) -> Remote<T> {
+		inner.acquire();
 		Remote {
 			inner
 		}
@@ -114,18 +629,91 @@ impl<T: ?Sized> Remote<T> {
 		&self.inner.queue
 	}
 
+	/// Identifier of the actor.
+	pub fn id(&self) -> ActorId {
+		self.inner.id()
+	}
+
+	/// Human-readable name of the actor, if any.
+	pub fn name(&self) -> Option<String> {
+		self.inner.name()
+	}
+
+	/// Set the human-readable name of the actor.
+	///
+	/// The name is only used to identify the actor in logs, and does not need to be unique.
+	/// See [`Registry`](crate::Registry) to look up actors by name.
+	pub fn set_name<N: Into<String>>(&self, name: Option<N>) {
+		self.inner.set_name(name.map(Into::into))
+	}
+
+	/// Stop the actor.
+	///
+	/// Events already sent to the actor are handled normally, but every event sent from now on is
+	/// rejected: its future resolves into an error.
+	/// Once the pending events are handled, the lifecycle hooks are called, if any, and the
+	/// actor state is dropped in the actor's thread.
+	/// The actor pointers remain valid but can no longer be used to reach the actor.
+	///
+	/// Calling this function more than once has no effect.
+	pub fn stop(&self) {
+		self.inner.stop()
+	}
+
+	/// Checks if the actor has been stopped.
+	///
+	/// This is also the case once every `Remote` and `Local` pointer to the actor has been
+	/// dropped.
+	pub fn is_stopped(&self) -> bool {
+		self.inner.is_stopping()
+	}
+
+	/// Checks if the actor has failed.
+	///
+	/// An actor fails when its constructor, one of its handlers or lifecycle hooks panics.
+	/// A failed actor is stopped: its state is dropped, and every event sent to it is rejected.
+	pub fn is_failed(&self) -> bool {
+		self.inner.is_failed()
+	}
+
+	/// Capacity of the actor mailbox.
+	///
+	/// The mailbox is unbounded if `None`.
+	pub fn capacity(&self) -> Option<Capacity> {
+		self.inner.mailbox.capacity()
+	}
+
+	/// Set the capacity of the actor mailbox.
+	///
+	/// The capacity limits the number of events sent to the actor that are not being handled yet.
+	/// Events sent to a full mailbox are handled according to the [`Overflow`](crate::Overflow)
+	/// policy. Events already in the mailbox are not affected.
+	pub fn set_capacity(&self, capacity: Option<Capacity>) {
+		self.inner.mailbox.set_capacity(capacity)
+	}
+
+	/// Default time given to the actor to respond to an event.
+	pub fn reply_timeout(&self) -> Option<Duration> {
+		self.inner.reply_timeout()
+	}
+
+	/// Set the default time given to the actor to respond to an event.
+	///
+	/// The futures of the events sent from now on resolve into [`SendError::Timeout`] if the
+	/// actor does not respond in time. The timeout is measured with the clock of the actor's
+	/// queue, from the moment the event is sent, and elapses even if the actor's thread is busy
+	/// or stalled.
+	/// An event that is not being handled yet when its timeout elapses is dropped.
+	pub fn set_reply_timeout(&self, timeout: Option<Duration>) {
+		*self.inner.reply_timeout.lock() = timeout
+	}
+
 	/// Convert this pointer to a local pointer.
 	///
 	/// Return a local pointer to this pointer actor if `local` resides in the same thread as
 	/// the pointed actor, or `None`.
 	/// The `local` object is used as a proof that the conversion is valid.
-	///
-	/// # Safety
-	/// Even if the actor resides in the local thread, it may not be initialized yet.
-	/// As such, it is impossible to cast a remote pointer to a local pointer in a safe way.
-	/// Caller must ensure that the remote actor has not been created with [`Remote::from`], or
-	/// that it has been initialized.
-	pub unsafe fn local_to<L: ThreadLocal>(&self, local: &L) -> Option<Local<T>> {
+	pub fn local_to<L: ThreadLocal>(&self, local: &L) -> Option<Local<T>> {
 		if self.queue() == local.queue() {
 			Some(Local::from_inner(self.inner.clone()))
 		} else {
@@ -133,59 +721,134 @@ impl<T: ?Sized> Remote<T> {
 		}
 	}
 
-	pub fn send<E: Event>(&self, event: E) -> Future<T, E::Response> where E: 'static, T: 'static + Handler<E> {
+	/// Send an event to the actor.
+	///
+	/// The returned future resolves into the actor's response, and panics if the event could not
+	/// be handled: see [`Remote::ask`] to handle the failure instead.
+	/// If the actor or queue mailbox is full with the [`Overflow::Wait`](crate::Overflow::Wait)
+	/// policy, the event is delivered once the future is polled and the mailbox has some room:
+	/// it is never delivered if the future is dropped before.
+	pub fn send<E: 'static + Event>(&self, event: E) -> Future<T, E::Response> where T: 'static + Handler<E> {
 		self.inner.queue.push(self.clone(), event)
 	}
 
-	pub(crate) fn post<E: Event>(&self, pending: Box<pending::ToReceive<E, T>>) -> LocalFuture<T, E::Response> where E: 'static, T: 'static + Handler<E> {
-		let future_state = pending.state().clone();
+	/// Send an event to the actor, and get its response or the reason why it could not be
+	/// handled.
+	///
+	/// The returned future resolves into the actor's response, or into a [`SendError`] if the
+	/// actor is stopped, its queue processor has terminated, its handler panicked, or its reply
+	/// timeout elapsed. Otherwise, this is equivalent to [`Remote::send`].
+	pub fn ask<E: 'static + Event>(&self, event: E) -> Ask<T, E::Response> where T: 'static + Handler<E> {
+		self.ask_with_priority(event, E::PRIORITY)
+	}
 
-		{
-			let mut actor = self.inner.actor.borrow_mut();
-			if actor.is_busy || !actor.inbox.is_empty() {
-				actor.inbox.push_front(pending);
-				return LocalFuture::new(future_state)
-			}
-		}
+	/// Send an event to the actor without waiting for its response.
+	///
+	/// This is cheaper than [`Remote::send`] since no response is tracked: the event is dropped
+	/// silently if it cannot be handled, or if a mailbox is full, whatever its overflow policy.
+	/// Such events are never dropped by the [`Overflow::DropOldest`](crate::Overflow::DropOldest)
+	/// policy, nor cancelled.
+	///
+	/// The event itself is still allocated. With an asynchronous handler, the handler future is
+	/// boxed and wrapped for the processor: about 3 allocations per event, against 1 with a
+	/// synchronous handler. If the actor is busy, a slot is also allocated to hand the handler
+	/// future over to the processor once the event leaves the inbox.
+	pub fn tell<E: 'static + Event>(&self, event: E) where T: 'static + Handler<E> {
+		self.inner.queue.notify(self.clone(), event, E::PRIORITY)
+	}
 
-		pending.process();
-		LocalFuture::new(future_state)
+	/// Send an event to the actor with the given priority, instead of [`Event::PRIORITY`].
+	///
+	/// See [`Remote::send`] and [`Priority`].
+	pub fn send_with_priority<E: 'static + Event>(&self, event: E, priority: Priority) -> Future<T, E::Response> where T: 'static + Handler<E> {
+		self.inner.queue.push_with_priority(self.clone(), event, priority)
 	}
 
-	pub(crate) fn post_any(&self, pending: Box<dyn Pending>) {
-		{
-			let mut actor = self.inner.actor.borrow_mut();
-			if actor.is_busy || !actor.inbox.is_empty() {
-				actor.inbox.push_front(pending);
-				return
-			}
+	/// Send an event to the actor with the given priority, and get its response or the reason why
+	/// it could not be handled.
+	///
+	/// See [`Remote::ask`] and [`Priority`].
+	pub fn ask_with_priority<E: 'static + Event>(&self, event: E, priority: Priority) -> Ask<T, E::Response> where T: 'static + Handler<E> {
+		self.inner.queue.request(self.clone(), event, priority)
+	}
+
+	/// Send an event to the actor, failing immediately if it cannot be delivered.
+	///
+	/// Returns an error if the actor is stopped, if its queue processor has terminated, or if
+	/// the actor or queue mailbox is full and the event would have to wait or be dropped.
+	/// With the [`Overflow::DropOldest`](crate::Overflow::DropOldest) policy, the oldest event is
+	/// dropped instead.
+	/// Otherwise, this is equivalent to [`Remote::ask`].
+	pub fn try_send<E: 'static + Event>(&self, event: E) -> Result<Ask<T, E::Response>, SendError> where T: 'static + Handler<E> {
+		self.try_send_with_priority(event, E::PRIORITY)
+	}
+
+	/// Send an event to the actor with the given priority, failing immediately if it cannot be
+	/// delivered.
+	///
+	/// See [`Remote::try_send`] and [`Priority`].
+	pub fn try_send_with_priority<E: 'static + Event>(&self, event: E, priority: Priority) -> Result<Ask<T, E::Response>, SendError> where T: 'static + Handler<E> {
+		if self.is_stopped() {
+			Err(SendError::ActorStopped)
+		} else if self.inner.queue.is_terminated() {
+			Err(SendError::QueueClosed)
+		} else {
+			self.inner.queue.try_push(self.clone(), event, priority)
 		}
+	}
 
-		pending.process()
+	/// Send an event to the actor once the given delay has elapsed.
+	///
+	/// The delay is measured with the clock of the actor's queue, and the event is sent by the
+	/// queue processor. The actor is kept alive until then.
+	/// The returned handle can be used to cancel the sending.
+	pub fn send_after<E: 'static + Event>(&self, delay: Duration, event: E) -> Timer where T: 'static + Handler<E> {
+		let remote = self.clone();
+		self.inner.queue.schedule(delay, Action::Once(Box::new(move || {
+			remote.tell(event)
+		})))
 	}
 
-	/// Restart the remote events execution.
+	/// Send an event created by `factory` to the actor every `period`.
 	///
-	/// This must be called from the actor's thread,
-	/// and only when no futures bound to this actor are executing.
-	pub(crate) unsafe fn restart(&self) {
-		// check if there is any pending events.
-		let pending = {
-			let mut actor = self.inner.actor.borrow_mut();
-			actor.is_busy = false;
-			actor.inbox.pop_back()
-		};
+	/// The first event is sent after one period. The timer is cancelled once the actor is
+	/// stopped, or with the returned handle.
+	pub fn send_interval<E: 'static + Event, F>(&self, period: Duration, mut factory: F) -> Timer where T: 'static + Handler<E>, F: 'static + Send + FnMut() -> E {
+		let remote = self.downgrade();
+		self.inner.queue.schedule(period, Action::Repeat(period, Box::new(move || {
+			match remote.upgrade() {
+				Some(remote) => {
+					remote.tell(factory());
+					true
+				},
+				None => false
+			}
+		})))
+	}
 
-		// if there is, process it since the actor is not busy anymore.
-		if let Some(pending) = pending {
-			pending.process()
-		}
+	pub(crate) fn post<E: 'static + Event>(&self, pending: Box<pending::ToReceive<E, T>>) -> LocalFuture<T, E::Response> where T: 'static + Handler<E> {
+		let future = LocalFuture::new(pending.state().clone());
+		self.inner.post_any(pending);
+		future
 	}
 
-	pub fn subscribe<E: Event>(&self, subscriber: Remote<dyn Handler<E>>) -> Future<T, bool> where E: 'static, T: 'static + Emitter<E> {
+	pub fn subscribe<E: 'static + Event>(&self, subscriber: Remote<dyn Handler<E>>) -> Future<T, bool> where T: 'static + Sized + Emitter<E> {
 		self.send(SubscriptionEvent::Subscribe(subscriber))
 	}
 
+	/// Convert this pointer into a pointer to an unsized type, such as a trait object.
+	///
+	/// With the `nightly` feature, this is done implicitly by unsized coercion.
+	/// Prefer the [`upcast!`](crate::upcast) macro, which guarantees that `f` is an unsizing
+	/// coercion.
+	///
+	/// # Safety
+	/// `f` must return its input pointer, only adding the pointer metadata of `U`.
+	pub unsafe fn upcast_with<U: ?Sized, F>(self, f: F) -> Remote<U> where T: Sized, F: FnOnce(*const T) -> *const U {
+		let ptr = Arc::into_raw(self.inner.clone()) as *const T;
+		Remote::from_inner(Arc::from_raw(f(ptr) as *const Inner<U>))
+	}
+
 	pub fn downgrade(&self) -> WeakRemote<T> {
 		WeakRemote {
 			inner: Arc::downgrade(&self.inner)
@@ -193,11 +856,35 @@ impl<T: ?Sized> Remote<T> {
 	}
 }
 
+/// Convert a [`Remote`] pointer into a pointer to an unsized type, such as a trait object.
+///
+/// ```ignore
+/// let remote: Remote<dyn Handler<Event>> = bottle::upcast!(remote => dyn Handler<Event>);
+/// ```
+///
+/// This is only required without the `nightly` feature, where pointers cannot be coerced
+/// implicitly.
+#[macro_export]
+macro_rules! upcast {
+	($remote:expr => $target:ty) => {
+		match $remote {
+			remote => unsafe {
+				// Safe because the closure can only perform an unsizing coercion.
+				$crate::Remote::upcast_with(remote, |ptr| -> *const $target { ptr })
+			}
+		}
+	}
+}
+
 impl<T: ?Sized> Clone for Remote<T> {
 	fn clone(&self) -> Remote<T> {
-		Remote {
-			inner: self.inner.clone()
-		}
+		Remote::from_inner(self.inner.clone())
+	}
+}
+
+impl<T: ?Sized> Drop for Remote<T> {
+	fn drop(&mut self) {
+		self.inner.drop_handle()
 	}
 }
 
@@ -209,6 +896,12 @@ impl<T: ?Sized> PartialEq for Remote<T> {
 
 impl<T: ?Sized> Eq for Remote<T> {}
 
+impl<T: ?Sized> fmt::Debug for Remote<T> {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		f.debug_struct("Remote").field("id", &self.id()).field("name", &self.name()).finish()
+	}
+}
+
 impl<T: ?Sized> Hash for Remote<T> {
 	fn hash<H: Hasher>(&self, h: &mut H) {
 		(&*self.inner as *const Inner<T>).hash(h)
@@ -220,15 +913,21 @@ pub struct WeakRemote<T: ?Sized> {
 	pub(crate) inner: Weak<Inner<T>>
 }
 
+#[cfg(feature = "nightly")]
 impl<T: ?Sized + Unsize<U>, U: ?Sized> CoerceUnsized<WeakRemote<U>> for WeakRemote<T> {}
+#[cfg(feature = "nightly")]
 impl<T: ?Sized + Unsize<U>, U: ?Sized> DispatchFromDyn<WeakRemote<U>> for WeakRemote<T> {}
 
 unsafe impl<T: ?Sized> Send for WeakRemote<T> {}
 unsafe impl<T: ?Sized> Sync for WeakRemote<T> {}
 
 impl<T: ?Sized> WeakRemote<T> {
+	/// Attempts to upgrade the weak pointer.
+	///
+	/// Returns `None` if the actor has been dropped, or if it is stopping.
 	pub fn upgrade(&self) -> Option<Remote<T>> {
-		if let Some(inner) = self.inner.upgrade() {
+		let inner = self.inner.upgrade()?;
+		if inner.try_acquire() {
 			Some(Remote {
 				inner
 			})
@@ -237,12 +936,23 @@ impl<T: ?Sized> WeakRemote<T> {
 		}
 	}
 
-	pub fn send<E: Event>(&self, event: E) -> Option<Future<T, E::Response>> where E: 'static, T: 'static + Handler<E> {
-		if let Some(remote) = self.upgrade() {
-			Some(remote.send(event))
-		} else {
-			None
-		}
+	pub fn send<E: 'static + Event>(&self, event: E) -> Option<Future<T, E::Response>> where T: 'static + Handler<E> {
+		self.upgrade().map(|remote| remote.send(event))
+	}
+
+	/// Send an event to the actor, and get its response or the reason why it could not be
+	/// handled.
+	///
+	/// Returns `None` if the actor is stopping or has been dropped. See [`Remote::ask`].
+	pub fn ask<E: 'static + Event>(&self, event: E) -> Option<Ask<T, E::Response>> where T: 'static + Handler<E> {
+		self.upgrade().map(|remote| remote.ask(event))
+	}
+
+	/// Checks if the actor is stopping or has been dropped.
+	///
+	/// If so, [`WeakRemote::upgrade`] will always return `None`.
+	pub fn is_stopped(&self) -> bool {
+		self.inner.upgrade().is_none_or(|inner| inner.is_stopping())
 	}
 }
 
@@ -0,0 +1,126 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread::JoinHandle;
+use crate::{EventQueue, EventQueueRef, Summary};
+
+/// How a [`Runtime`] chooses the worker of a new actor.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Placement {
+	/// Each worker in turn.
+	RoundRobin,
+
+	/// The worker with the fewest events waiting to be handled.
+	LeastLoaded,
+
+	/// The worker of the given index.
+	Pinned(usize)
+}
+
+struct Worker {
+	queue: EventQueueRef,
+	thread: Option<JoinHandle<Summary>>
+}
+
+/// A pool of worker threads, each processing its own event queue.
+///
+/// Actors are attached to a worker by creating them with one of the queues given by
+/// [`Runtime::queue`].
+///
+/// # Shutdown
+/// [`Runtime::shutdown`] closes every queue: each worker then terminates once its queue is
+/// drained, as described in [`EventQueueProcessor`](crate::EventQueueProcessor).
+/// [`Runtime::join`] waits for every worker to terminate.
+/// Dropping the runtime shuts it down and joins the workers.
+pub struct Runtime {
+	workers: Vec<Worker>,
+
+	/// Next worker for the round-robin placement.
+	next: AtomicUsize
+}
+
+impl Runtime {
+	/// Spawn a runtime with the given number of worker threads.
+	///
+	/// # Panics
+	/// Panics if `workers` is 0, or if a thread cannot be spawned.
+	pub fn new(workers: usize) -> Runtime {
+		assert!(workers > 0, "runtime must have at least one worker");
+
+		let workers = (0..workers).map(|i| {
+			let queue = EventQueue::new();
+			let reference = queue.reference();
+			let thread = std::thread::Builder::new()
+				.name(format!("bottle-worker-{}", i))
+				.spawn(move || queue.run_blocking())
+				.expect("unable to spawn worker thread");
+
+			Worker {
+				queue: reference,
+				thread: Some(thread)
+			}
+		}).collect();
+
+		Runtime {
+			workers,
+			next: AtomicUsize::new(0)
+		}
+	}
+
+	/// Number of worker threads.
+	pub fn workers(&self) -> usize {
+		self.workers.len()
+	}
+
+	/// Get the queue of a worker, chosen with the given placement strategy.
+	///
+	/// # Panics
+	/// Panics if the index of a [`Placement::Pinned`] worker is out of bounds.
+	pub fn queue(&self, placement: Placement) -> EventQueueRef {
+		let index = match placement {
+			Placement::RoundRobin => self.next.fetch_add(1, Ordering::Relaxed) % self.workers.len(),
+			Placement::LeastLoaded => {
+				(0..self.workers.len()).min_by_key(|&i| self.workers[i].queue.load()).unwrap()
+			},
+			Placement::Pinned(index) => {
+				assert!(index < self.workers.len(), "worker index out of bounds");
+				index
+			}
+		};
+
+		self.workers[index].queue.clone()
+	}
+
+	/// Close the queue of every worker.
+	///
+	/// See [`EventQueueRef::close`].
+	pub fn shutdown(&self) {
+		for worker in &self.workers {
+			worker.queue.close()
+		}
+	}
+
+	/// Wait for every worker to terminate, and return the summary of their work.
+	///
+	/// The runtime is not shut down: the workers terminate once their queue is closed.
+	/// If a worker panicked, the panic is propagated.
+	pub fn join(mut self) -> Vec<Summary> {
+		self.join_workers()
+	}
+
+	fn join_workers(&mut self) -> Vec<Summary> {
+		self.workers.iter_mut().filter_map(|worker| worker.thread.take()).map(|thread| {
+			match thread.join() {
+				Ok(summary) => summary,
+				Err(e) => std::panic::resume_unwind(e)
+			}
+		}).collect()
+	}
+}
+
+impl Drop for Runtime {
+	fn drop(&mut self) {
+		self.shutdown();
+		if !std::thread::panicking() {
+			self.join_workers();
+		}
+	}
+}
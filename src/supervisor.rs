@@ -0,0 +1,335 @@
+use std::any::Any;
+use std::sync::Arc;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+use std::collections::VecDeque;
+use crate::{Output, Event, EventQueueRef, Handler, Remote, WeakRemote, Lifecycle, ThreadLocal};
+#[cfg(feature = "nightly")]
+use crate::Receiver;
+#[cfg(not(feature = "nightly"))]
+use crate::Context;
+
+/// Restart strategy of a supervisor.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Strategy {
+	/// Only the failed child is restarted.
+	OneForOne,
+
+	/// Every child is restarted when one of them fails.
+	OneForAll,
+
+	/// The failed child is restarted along with every child added after it.
+	RestForOne
+}
+
+/// Maximum restart intensity of a supervisor.
+///
+/// If more than `max_restarts` restarts happen within `period`, the supervisor gives up: it stops
+/// all of its children and fails.
+/// The period is measured with the clock of the supervisor's queue.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Intensity {
+	pub max_restarts: usize,
+	pub period: Duration
+}
+
+impl Intensity {
+	pub fn new(max_restarts: usize, period: Duration) -> Intensity {
+		Intensity {
+			max_restarts,
+			period
+		}
+	}
+}
+
+impl Default for Intensity {
+	/// One restart every five seconds.
+	fn default() -> Intensity {
+		Intensity::new(1, Duration::from_secs(5))
+	}
+}
+
+/// Identifier of a supervised child.
+pub struct ChildId<T> {
+	index: usize,
+	marker: PhantomData<fn() -> T>
+}
+
+impl<T> Clone for ChildId<T> {
+	fn clone(&self) -> ChildId<T> {
+		*self
+	}
+}
+
+impl<T> Copy for ChildId<T> {}
+
+/// Type erased supervised child.
+trait Child: Send {
+	/// Spawn a new instance of the child, calling `monitor` if it fails.
+	fn start(&mut self, monitor: Box<dyn FnOnce() + Send>);
+
+	/// Stop the current instance of the child, if any.
+	fn stop(&mut self);
+
+	/// The current `Option<Remote<T>>` of the child.
+	fn remote(&self) -> &dyn Any;
+}
+
+struct Spec<T> {
+	queue: EventQueueRef,
+	factory: Arc<dyn Send + Sync + Fn() -> T>,
+	remote: Option<Remote<T>>
+}
+
+impl<T: 'static + Lifecycle> Child for Spec<T> {
+	fn start(&mut self, monitor: Box<dyn FnOnce() + Send>) {
+		let factory = self.factory.clone();
+		let remote = Remote::spawn_from(self.queue.clone(), move || factory());
+		remote.inner.on_failure(monitor);
+		self.remote = Some(remote)
+	}
+
+	fn stop(&mut self) {
+		if let Some(remote) = self.remote.take() {
+			remote.stop()
+		}
+	}
+
+	fn remote(&self) -> &dyn Any {
+		&self.remote
+	}
+}
+
+struct Entry {
+	child: Box<dyn Child>,
+
+	/// Incremented each time the child is restarted, to ignore outdated failures.
+	generation: usize
+}
+
+/// An actor restarting its children when they fail.
+///
+/// Children are created from factories, in the queue given for each of them, and must implement
+/// [`Lifecycle`].
+/// They are started with the supervisor, in the order they have been added, and stopped in the
+/// reverse order when the supervisor stops. When a child fails, the supervisor restarts it
+/// according to its [`Strategy`].
+///
+/// If the restart [`Intensity`] is exceeded, the supervisor stops its children and fails itself,
+/// so that supervisors can be nested: a supervisor is a child like any other.
+///
+/// A supervisor must be created with [`Remote::spawn`] for its children to be started.
+pub struct Supervisor {
+	strategy: Strategy,
+	intensity: Intensity,
+	children: Vec<Entry>,
+	restarts: VecDeque<Instant>,
+	this: Option<WeakRemote<Supervisor>>
+}
+
+impl Supervisor {
+	pub fn new(strategy: Strategy, intensity: Intensity) -> Supervisor {
+		Supervisor {
+			strategy,
+			intensity,
+			children: Vec::new(),
+			restarts: VecDeque::new(),
+			this: None
+		}
+	}
+
+	pub fn strategy(&self) -> Strategy {
+		self.strategy
+	}
+
+	pub fn intensity(&self) -> Intensity {
+		self.intensity
+	}
+
+	/// Add a child, created by `factory` in the given queue each time it is (re)started.
+	///
+	/// Use the returned identifier with the [`GetChild`] event to reach the current instance of the
+	/// child.
+	pub fn add<T, F>(&mut self, queue: EventQueueRef, factory: F) -> ChildId<T> where T: 'static + Lifecycle, F: 'static + Send + Sync + Fn() -> T {
+		let index = self.children.len();
+		self.children.push(Entry {
+			child: Box::new(Spec {
+				queue,
+				factory: Arc::new(factory),
+				remote: None
+			}),
+			generation: 0
+		});
+
+		ChildId {
+			index,
+			marker: PhantomData
+		}
+	}
+
+	/// Start every child, once the supervisor itself is started.
+	fn start(&mut self, this: WeakRemote<Supervisor>) {
+		self.this = Some(this);
+		for index in 0..self.children.len() {
+			self.start_child(index)
+		}
+	}
+
+	fn start_child(&mut self, index: usize) {
+		let entry = &mut self.children[index];
+		entry.generation += 1;
+
+		let supervisor = self.this.clone();
+		let failed = ChildFailed {
+			index,
+			generation: entry.generation
+		};
+
+		entry.child.start(Box::new(move || {
+			if let Some(supervisor) = supervisor.and_then(|s| s.upgrade()) {
+				// The failure must not be dropped by the capacity of the supervisor mailboxes.
+				let queue = supervisor.queue().clone();
+				queue.notify_unbounded(supervisor, failed, ChildFailed::PRIORITY);
+			}
+		}))
+	}
+
+	/// Stop the children in `range`, in reverse order.
+	fn stop_children(&mut self, range: std::ops::Range<usize>) {
+		for entry in self.children[range].iter_mut().rev() {
+			entry.child.stop()
+		}
+	}
+
+	/// Record a restart at the given time, and check that the restart intensity is not exceeded.
+	fn may_restart(&mut self, now: Instant) -> bool {
+		while let Some(t) = self.restarts.front() {
+			if now.duration_since(*t) > self.intensity.period {
+				self.restarts.pop_front();
+			} else {
+				break
+			}
+		}
+
+		self.restarts.push_back(now);
+		self.restarts.len() <= self.intensity.max_restarts
+	}
+
+	/// Restart the children after a failure, according to the strategy.
+	///
+	/// `now` is the time of the supervisor queue clock.
+	fn restart(&mut self, failed: ChildFailed, now: Instant) {
+		if self.children[failed.index].generation != failed.generation {
+			// This instance has already been replaced.
+			return
+		}
+
+		if !self.may_restart(now) {
+			let len = self.children.len();
+			self.stop_children(0..len);
+			if let Some(this) = self.this.as_ref().and_then(WeakRemote::upgrade) {
+				// The supervisor is busy handling the failure: it fails once it is done.
+				this.inner.fail_when_idle()
+			}
+
+			return
+		}
+
+		let restarted = match self.strategy {
+			Strategy::OneForOne => failed.index..(failed.index + 1),
+			Strategy::OneForAll => 0..self.children.len(),
+			Strategy::RestForOne => failed.index..self.children.len()
+		};
+
+		self.stop_children(restarted.clone());
+		for index in restarted {
+			self.start_child(index)
+		}
+	}
+
+	fn child<T: 'static>(&self, id: ChildId<T>) -> Option<Remote<T>> {
+		self.children.get(id.index).and_then(|entry| {
+			entry.child.remote().downcast_ref::<Option<Remote<T>>>().cloned().flatten()
+		})
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Lifecycle for Supervisor {
+	fn started<'a>(mut self: Receiver<'a, Self>) -> Output<'a, ()> {
+		let this = self.as_remote().downgrade();
+		self.start(this);
+		Output::Now(())
+	}
+
+	fn stopped<'a>(mut self: Receiver<'a, Self>) -> Output<'a, ()> {
+		let len = self.children.len();
+		self.stop_children(0..len);
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Lifecycle for Supervisor {
+	fn started<'a>(&'a mut self, ctx: &'a mut Context<Self>) -> Output<'a, ()> {
+		self.start(ctx.as_remote().downgrade());
+		Output::Now(())
+	}
+
+	fn stopped<'a>(&'a mut self, _ctx: &'a mut Context<Self>) -> Output<'a, ()> {
+		let len = self.children.len();
+		self.stop_children(0..len);
+		Output::Now(())
+	}
+}
+
+/// Sent to the supervisor when one of its children fails.
+struct ChildFailed {
+	index: usize,
+	generation: usize
+}
+
+impl Event for ChildFailed {
+	type Response = ();
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<ChildFailed> for Supervisor {
+	fn handle<'a>(mut self: Receiver<'a, Self>, event: ChildFailed) -> Output<'a, ()> {
+		let now = self.queue().clock().now();
+		self.restart(event, now);
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<ChildFailed> for Supervisor {
+	fn handle<'a>(&'a mut self, ctx: &'a mut Context<Self>, event: ChildFailed) -> Output<'a, ()> {
+		self.restart(event, ctx.queue().clock().now());
+		Output::Now(())
+	}
+}
+
+/// Get the current instance of a supervised child.
+///
+/// Responds `None` if the child is not running, because the supervisor has not been started yet or
+/// is stopped.
+pub struct GetChild<T>(pub ChildId<T>);
+
+impl<T: 'static> Event for GetChild<T> {
+	type Response = Option<Remote<T>>;
+}
+
+#[cfg(feature = "nightly")]
+impl<T: 'static> Handler<GetChild<T>> for Supervisor {
+	fn handle<'a>(self: Receiver<'a, Self>, GetChild(id): GetChild<T>) -> Output<'a, Option<Remote<T>>> {
+		Output::Now(self.child(id))
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl<T: 'static> Handler<GetChild<T>> for Supervisor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, GetChild(id): GetChild<T>) -> Output<'a, Option<Remote<T>>> {
+		Output::Now(self.child(id))
+	}
+}
@@ -0,0 +1,634 @@
+use std::sync::{Arc, OnceLock};
+use std::task::Wake;
+use std::time::{Duration, Instant};
+use std::task::{Waker, Context, Poll};
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::collections::{BTreeMap, HashMap};
+use std::panic::{self, AssertUnwindSafe};
+use parking_lot::{Mutex, MutexGuard, Condvar};
+use crate::EventQueueRef;
+
+/// Source of time of an event queue.
+///
+/// The default clock is [`SystemClock`]. A [`ManualClock`] can be used to control the time in
+/// tests.
+pub trait Clock: Send + Sync {
+	/// Current time.
+	fn now(&self) -> Instant;
+
+	/// Call the waker once the given time is reached.
+	///
+	/// Returns a key identifying the wake-up, to cancel it with [`Clock::cancel`].
+	fn wake_at(&self, deadline: Instant, waker: Waker) -> u64;
+
+	/// Cancel a wake-up, and drop its waker.
+	///
+	/// Has no effect if the wake-up has already been performed.
+	fn cancel(&self, key: u64);
+}
+
+/// The system monotonic clock.
+///
+/// Wake-ups are performed by a single background thread shared by every queue.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+	fn now(&self) -> Instant {
+		Instant::now()
+	}
+
+	fn wake_at(&self, deadline: Instant, waker: Waker) -> u64 {
+		let thread = timer_thread();
+		let key = thread.wakeups.lock().insert(deadline, waker);
+		thread.condvar.notify_one();
+		key
+	}
+
+	fn cancel(&self, key: u64) {
+		let waker = timer_thread().wakeups.lock().remove(key);
+		// The waker is dropped outside of the lock.
+		std::mem::drop(waker)
+	}
+}
+
+/// Pending wake-ups of the timer thread, ordered by deadline.
+#[derive(Default)]
+struct Wakeups {
+	queue: BTreeMap<(Instant, u64), Waker>,
+
+	/// Deadline of each wake-up.
+	deadlines: HashMap<u64, Instant>,
+
+	next_key: u64
+}
+
+impl Wakeups {
+	fn insert(&mut self, deadline: Instant, waker: Waker) -> u64 {
+		let key = self.next_key;
+		self.next_key += 1;
+		self.queue.insert((deadline, key), waker);
+		self.deadlines.insert(key, deadline);
+		key
+	}
+
+	fn remove(&mut self, key: u64) -> Option<Waker> {
+		let deadline = self.deadlines.remove(&key)?;
+		self.queue.remove(&(deadline, key))
+	}
+
+	/// Deadline of the next wake-up.
+	fn next(&self) -> Option<Instant> {
+		self.queue.keys().next().map(|&(deadline, _)| deadline)
+	}
+
+	fn pop(&mut self) -> Option<Waker> {
+		let ((_, key), waker) = self.queue.pop_first()?;
+		self.deadlines.remove(&key);
+		Some(waker)
+	}
+}
+
+struct TimerThread {
+	wakeups: Mutex<Wakeups>,
+	condvar: Condvar
+}
+
+fn timer_thread() -> &'static TimerThread {
+	static THREAD: OnceLock<TimerThread> = OnceLock::new();
+	THREAD.get_or_init(|| {
+		std::thread::Builder::new()
+			.name("bottle-timer".to_string())
+			.spawn(run_timer_thread)
+			.expect("unable to spawn timer thread");
+
+		TimerThread {
+			wakeups: Mutex::new(Wakeups::default()),
+			condvar: Condvar::new()
+		}
+	})
+}
+
+fn run_timer_thread() {
+	let thread = timer_thread();
+	let mut wakeups = thread.wakeups.lock();
+	loop {
+		let now = Instant::now();
+		match wakeups.next() {
+			Some(deadline) if deadline <= now => {
+				let waker = wakeups.pop().unwrap();
+				MutexGuard::unlocked(&mut wakeups, || waker.wake())
+			},
+			Some(deadline) => {
+				thread.condvar.wait_until(&mut wakeups, deadline);
+			},
+			None => thread.condvar.wait(&mut wakeups)
+		}
+	}
+}
+
+struct Wakeup {
+	deadline: Instant,
+	key: u64,
+	waker: Waker
+}
+
+/// A clock whose time only changes when advanced explicitly.
+///
+/// Useful to test actors with virtual time.
+pub struct ManualClock {
+	now: Mutex<Instant>,
+	wakeups: Mutex<Vec<Wakeup>>,
+	next_key: AtomicU64
+}
+
+impl ManualClock {
+	/// Create a new clock, starting at the current system time.
+	pub fn new() -> ManualClock {
+		ManualClock {
+			now: Mutex::new(Instant::now()),
+			wakeups: Mutex::new(Vec::new()),
+			next_key: AtomicU64::new(0)
+		}
+	}
+
+	/// Advance the time, and wake up the queues whose timers are expired.
+	pub fn advance(&self, duration: Duration) {
+		let now = {
+			let mut now = self.now.lock();
+			*now += duration;
+			*now
+		};
+
+		let expired: Vec<_> = {
+			let mut wakeups = self.wakeups.lock();
+			let (expired, pending) = wakeups.drain(..).partition(|wakeup| wakeup.deadline <= now);
+			*wakeups = pending;
+			expired
+		};
+
+		for wakeup in expired {
+			wakeup.waker.wake()
+		}
+	}
+}
+
+impl Default for ManualClock {
+	fn default() -> ManualClock {
+		ManualClock::new()
+	}
+}
+
+impl Clock for ManualClock {
+	fn now(&self) -> Instant {
+		*self.now.lock()
+	}
+
+	fn wake_at(&self, deadline: Instant, waker: Waker) -> u64 {
+		let key = self.next_key.fetch_add(1, AtomicOrdering::Relaxed);
+		if deadline <= self.now() {
+			waker.wake()
+		} else {
+			self.wakeups.lock().push(Wakeup { deadline, key, waker })
+		}
+
+		key
+	}
+
+	fn cancel(&self, key: u64) {
+		let wakeup = {
+			let mut wakeups = self.wakeups.lock();
+			let i = wakeups.iter().position(|wakeup| wakeup.key == key);
+			i.map(|i| wakeups.swap_remove(i))
+		};
+
+		// The waker is dropped outside of the lock.
+		std::mem::drop(wakeup)
+	}
+}
+
+/// Duration of a tick of the timer wheel.
+const RESOLUTION: Duration = Duration::from_millis(1);
+
+/// Number of slots of the timer wheel.
+const SLOTS: u64 = 256;
+
+fn ticks(duration: Duration) -> u64 {
+	duration.as_nanos().div_ceil(RESOLUTION.as_nanos()) as u64
+}
+
+pub(crate) enum Action {
+	Once(Box<dyn FnOnce() + Send>),
+
+	/// Repeated with the given period, as long as it returns `true`.
+	Repeat(Duration, Box<dyn FnMut() -> bool + Send>)
+}
+
+struct Entry {
+	id: u64,
+	tick: u64,
+	action: Action
+}
+
+/// Hashed timer wheel.
+///
+/// Each timer is put in the slot of its deadline tick, modulo the number of slots.
+struct Wheel {
+	/// Time of the tick 0, set when the first timer is scheduled.
+	start: Option<Instant>,
+
+	/// Next tick to process.
+	tick: u64,
+
+	slots: Vec<Vec<Entry>>,
+
+	/// Deadline tick of each timer, or `None` while a repeated timer is running.
+	timers: HashMap<u64, Option<u64>>,
+
+	next_id: u64,
+
+	/// Time at which the processor will be woken up, with the clock wake-up.
+	armed: Option<(Instant, Armed)>,
+
+	/// Waker of the processor.
+	waker: Option<Waker>
+}
+
+impl Wheel {
+	fn new() -> Wheel {
+		Wheel {
+			start: None,
+			tick: 0,
+			slots: (0..SLOTS).map(|_| Vec::new()).collect(),
+			timers: HashMap::new(),
+			next_id: 0,
+			armed: None,
+			waker: None
+		}
+	}
+
+	fn instant(&self, tick: u64) -> Instant {
+		self.start.unwrap() + Duration::from_nanos(RESOLUTION.as_nanos() as u64 * tick)
+	}
+
+	fn insert(&mut self, id: u64, tick: u64, action: Action) {
+		let tick = std::cmp::max(tick, self.tick);
+		self.slots[(tick % SLOTS) as usize].push(Entry { id, tick, action });
+		self.timers.insert(id, Some(tick));
+	}
+
+	fn schedule(&mut self, now: Instant, delay: Duration, action: Action) -> (u64, u64) {
+		let start = *self.start.get_or_insert(now);
+		let id = self.next_id;
+		self.next_id += 1;
+
+		let tick = ticks((now + delay).saturating_duration_since(start));
+		self.insert(id, tick, action);
+		(id, tick)
+	}
+
+	fn cancel(&mut self, id: u64) -> Option<Entry> {
+		let tick = self.timers.remove(&id)??;
+		let slot = &mut self.slots[(tick % SLOTS) as usize];
+		let i = slot.iter().position(|entry| entry.id == id)?;
+		Some(slot.swap_remove(i))
+	}
+
+	/// Remove the expired timers.
+	fn expire(&mut self, now: Instant) -> Vec<Entry> {
+		let mut expired = Vec::new();
+		let target = match self.start {
+			Some(start) if now >= start => ((now - start).as_nanos() / RESOLUTION.as_nanos()) as u64,
+			_ => return expired
+		};
+
+		if target < self.tick {
+			return expired
+		}
+
+		let count = std::cmp::min(target - self.tick + 1, SLOTS);
+		for i in 0..count {
+			let slot = &mut self.slots[((self.tick + i) % SLOTS) as usize];
+			let mut j = 0;
+			while j < slot.len() {
+				if slot[j].tick <= target {
+					expired.push(slot.swap_remove(j))
+				} else {
+					j += 1
+				}
+			}
+		}
+
+		for entry in &expired {
+			match entry.action {
+				Action::Once(_) => self.timers.remove(&entry.id),
+				Action::Repeat(_, _) => self.timers.insert(entry.id, None)
+			};
+		}
+
+		expired.sort_by_key(|entry| (entry.tick, entry.id));
+		self.tick = target + 1;
+		expired
+	}
+
+	/// Deadline of the next timer.
+	fn next(&self) -> Option<Instant> {
+		for i in 0..SLOTS {
+			let tick = self.tick + i;
+			if self.slots[(tick % SLOTS) as usize].iter().any(|entry| entry.tick == tick) {
+				return Some(self.instant(tick))
+			}
+		}
+
+		self.slots.iter().flatten().map(|entry| entry.tick).min().map(|tick| self.instant(tick))
+	}
+
+	/// Arm the clock so that the processor is woken up at the given deadline, unless it is
+	/// already woken up before.
+	fn arm(&mut self, clock: &Arc<dyn Clock>, now: Instant, deadline: Instant) {
+		if self.armed.as_ref().is_none_or(|&(armed, _)| deadline < armed || armed <= now) {
+			if let Some(waker) = &self.waker {
+				let key = clock.wake_at(deadline, waker.clone());
+				if let Some((armed, previous)) = self.armed.replace((deadline, Armed(clock.clone(), key))) {
+					if armed > now {
+						// Superseded by the earlier wake-up.
+						previous.cancel()
+					}
+				}
+			}
+		}
+	}
+
+	/// Cancel the wake-up of the processor.
+	fn disarm(&mut self) {
+		if let Some((_, armed)) = self.armed.take() {
+			armed.cancel()
+		}
+	}
+}
+
+/// Wake-up of a processor, and the clock performing it.
+struct Armed(Arc<dyn Clock>, u64);
+
+impl Armed {
+	fn cancel(self) {
+		self.0.cancel(self.1)
+	}
+}
+
+/// Timers of an event queue, fired by its processor.
+pub(crate) struct Timers {
+	clock: Mutex<Arc<dyn Clock>>,
+	wheel: Mutex<Wheel>
+}
+
+impl Timers {
+	pub fn new() -> Timers {
+		Timers {
+			clock: Mutex::new(Arc::new(SystemClock)),
+			wheel: Mutex::new(Wheel::new())
+		}
+	}
+
+	pub fn clock(&self) -> Arc<dyn Clock> {
+		self.clock.lock().clone()
+	}
+
+	pub fn set_clock(&self, clock: Arc<dyn Clock>) {
+		*self.clock.lock() = clock;
+
+		// The processor is woken up to arm the new clock.
+		let waker = {
+			let mut wheel = self.wheel.lock();
+			wheel.disarm();
+			wheel.waker.clone()
+		};
+
+		if let Some(waker) = waker {
+			waker.wake()
+		}
+	}
+
+	pub fn schedule(&self, delay: Duration, action: Action) -> u64 {
+		let clock = self.clock();
+		let now = clock.now();
+		let mut wheel = self.wheel.lock();
+		let (id, tick) = wheel.schedule(now, delay, action);
+		let deadline = wheel.instant(tick);
+		wheel.arm(&clock, now, deadline);
+		id
+	}
+
+	pub fn cancel(&self, id: u64) {
+		let entry = self.wheel.lock().cancel(id);
+		// The action is dropped outside of the lock.
+		std::mem::drop(entry)
+	}
+
+	/// Run the expired timers, and make sure the processor is woken up for the next one.
+	pub fn fire(&self, waker: &Waker) {
+		let clock = self.clock();
+		let now = clock.now();
+		let expired = {
+			let mut wheel = self.wheel.lock();
+			if wheel.waker.as_ref().is_none_or(|w| !w.will_wake(waker)) {
+				wheel.waker = Some(waker.clone());
+				wheel.disarm()
+			}
+
+			wheel.expire(now)
+		};
+
+		for entry in expired {
+			match entry.action {
+				Action::Once(action) => action(),
+				Action::Repeat(period, mut action) => {
+					if let Ok(true) = panic::catch_unwind(AssertUnwindSafe(&mut action)) {
+						let mut wheel = self.wheel.lock();
+						if wheel.timers.get(&entry.id) == Some(&None) {
+							let tick = entry.tick + std::cmp::max(ticks(period), 1);
+							wheel.insert(entry.id, tick, Action::Repeat(period, action))
+						}
+					}
+				}
+			}
+		}
+
+		let mut wheel = self.wheel.lock();
+		if let Some(deadline) = wheel.next() {
+			wheel.arm(&clock, now, deadline)
+		}
+	}
+
+	/// Cancel every timer.
+	pub fn clear(&self) {
+		let slots = {
+			let mut wheel = self.wheel.lock();
+			wheel.timers.clear();
+			std::mem::replace(&mut wheel.slots, (0..SLOTS).map(|_| Vec::new()).collect())
+		};
+
+		std::mem::drop(slots)
+	}
+}
+
+/// Handle to a scheduled timer.
+///
+/// Dropping the handle does not cancel the timer.
+pub struct Timer {
+	queue: EventQueueRef,
+	id: u64
+}
+
+impl Timer {
+	pub(crate) fn new(queue: EventQueueRef, id: u64) -> Timer {
+		Timer {
+			queue,
+			id
+		}
+	}
+
+	/// Cancel the timer.
+	///
+	/// Has no effect if the timer has already fired, unless it is repeated.
+	pub fn cancel(&self) {
+		self.queue.timers().cancel(self.id)
+	}
+}
+
+/// Future that completes once a duration has elapsed.
+///
+/// It is driven by the timers of an event queue: see [`EventQueueRef::sleep`].
+pub struct Sleep {
+	queue: EventQueueRef,
+	deadline: Instant,
+	timer: Option<(u64, Arc<Mutex<Option<Waker>>>)>
+}
+
+impl Sleep {
+	pub(crate) fn new(queue: EventQueueRef, duration: Duration) -> Sleep {
+		let deadline = queue.timers().clock().now() + duration;
+		Sleep {
+			queue,
+			deadline,
+			timer: None
+		}
+	}
+}
+
+impl std::future::Future for Sleep {
+	type Output = ();
+
+	fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+		let this = &mut *self;
+		let timers = this.queue.timers();
+		let now = timers.clock().now();
+		if now >= this.deadline {
+			if let Some((id, _)) = this.timer.take() {
+				timers.cancel(id)
+			}
+
+			return Poll::Ready(())
+		}
+
+		match &this.timer {
+			Some((_, waker)) => *waker.lock() = Some(ctx.waker().clone()),
+			None => {
+				let waker = Arc::new(Mutex::new(Some(ctx.waker().clone())));
+				let timer_waker = waker.clone();
+				let id = timers.schedule(this.deadline - now, Action::Once(Box::new(move || {
+					if let Some(waker) = timer_waker.lock().take() {
+						waker.wake()
+					}
+				})));
+
+				this.timer = Some((id, waker))
+			}
+		}
+
+		Poll::Pending
+	}
+}
+
+impl Drop for Sleep {
+	fn drop(&mut self) {
+		if let Some((id, _)) = self.timer.take() {
+			self.queue.timers().cancel(id)
+		}
+	}
+}
+
+/// Future that completes once a time is reached, woken up by a clock directly.
+///
+/// Unlike [`Sleep`], it does not depend on any queue processor: reply timeouts must elapse even
+/// if the receiver's thread is stalled.
+pub(crate) struct Deadline {
+	clock: Arc<dyn Clock>,
+	deadline: Instant,
+
+	/// Waker of the task awaiting the deadline, and key of the clock wake-up, once armed.
+	armed: Option<(Arc<Slot>, u64)>
+}
+
+impl Deadline {
+	pub fn new(clock: Arc<dyn Clock>, duration: Duration) -> Deadline {
+		let deadline = clock.now() + duration;
+		Deadline {
+			clock,
+			deadline,
+			armed: None
+		}
+	}
+}
+
+impl std::future::Future for Deadline {
+	type Output = ();
+
+	fn poll(mut self: Pin<&mut Self>, ctx: &mut Context) -> Poll<()> {
+		let this = &mut *self;
+		if this.clock.now() >= this.deadline {
+			return Poll::Ready(())
+		}
+
+		let is_armed = match &this.armed {
+			Some((slot, _)) => slot.0.lock().replace(ctx.waker().clone()).is_some(),
+			None => false
+		};
+
+		if !is_armed {
+			// The clock is armed on the first poll, or again after an early wake-up.
+			let slot = Arc::new(Slot(Mutex::new(Some(ctx.waker().clone()))));
+			let key = this.clock.wake_at(this.deadline, Waker::from(slot.clone()));
+			this.armed = Some((slot, key))
+		}
+
+		Poll::Pending
+	}
+}
+
+impl Drop for Deadline {
+	fn drop(&mut self) {
+		// Release the clock wake-up, which would otherwise be kept until the deadline.
+		if let Some((slot, key)) = self.armed.take() {
+			slot.0.lock().take();
+			self.clock.cancel(key)
+		}
+	}
+}
+
+/// Waker given to the clock by a [`Deadline`], forwarding to the task awaiting it.
+struct Slot(Mutex<Option<Waker>>);
+
+impl Wake for Slot {
+	fn wake(self: Arc<Self>) {
+		self.wake_by_ref()
+	}
+
+	fn wake_by_ref(self: &Arc<Self>) {
+		let waker = self.0.lock().take();
+		if let Some(waker) = waker {
+			waker.wake()
+		}
+	}
+}
@@ -0,0 +1,127 @@
+//! Collecting the responses of the subscribers of a `Demux`.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::collections::HashMap;
+use std::sync::mpsc;
+use std::time::Duration;
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue, Demux, SendError, upcast};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+#[derive(Clone)]
+struct Add(u32);
+
+impl bottle::Event for Add {
+	type Response = u32;
+}
+
+/// Block the actor's thread until the gate is opened.
+struct Stall(mpsc::Sender<()>, mpsc::Receiver<()>);
+
+impl bottle::Event for Stall {
+	type Response = ();
+}
+
+struct Adder(u32);
+
+#[cfg(feature = "nightly")]
+impl Handler<Add> for Adder {
+	fn handle<'a>(self: Receiver<'a, Self>, Add(value): Add) -> Output<'a, u32> {
+		Output::Now(self.0 + value)
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Add> for Adder {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Add(value): Add) -> Output<'a, u32> {
+		Output::Now(self.0 + value)
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Stall> for Adder {
+	fn handle<'a>(self: Receiver<'a, Self>, Stall(stalled, gate): Stall) -> Output<'a, ()> {
+		stalled.send(()).unwrap();
+		let _ = gate.recv();
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Stall> for Adder {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Stall(stalled, gate): Stall) -> Output<'a, ()> {
+		stalled.send(()).unwrap();
+		let _ = gate.recv();
+		Output::Now(())
+	}
+}
+
+fn spawn(value: u32) -> (Remote<Adder>, std::thread::JoinHandle<()>) {
+	let queue = EventQueue::new();
+	let adder = Remote::new(queue.reference(), Adder(value));
+	let processor = std::thread::spawn(move || {
+		queue.run_blocking();
+	});
+
+	(adder, processor)
+}
+
+fn close(adder: Remote<Adder>, processor: std::thread::JoinHandle<()>) {
+	adder.queue().close();
+	processor.join().unwrap()
+}
+
+#[test]
+fn collects_every_response() {
+	let (a, a_processor) = spawn(1);
+	let (b, b_processor) = spawn(100);
+	let mut demux = Demux::new();
+	demux.subscribe(&upcast!(a.clone() => dyn Handler<Add>));
+	demux.subscribe(&upcast!(b.clone() => dyn Handler<Add>));
+
+	let responses: HashMap<_, _> = block_on(demux.broadcast(Add(1), None)).into_iter().map(|(subscriber, response)| {
+		(subscriber.id(), response)
+	}).collect();
+
+	assert_eq!(responses, HashMap::from([(a.id(), Ok(2)), (b.id(), Ok(101))]));
+	close(a, a_processor);
+	close(b, b_processor)
+}
+
+#[test]
+fn stalled_subscriber_times_out() {
+	let (a, a_processor) = spawn(1);
+	let (b, b_processor) = spawn(100);
+	let mut demux = Demux::new();
+	demux.subscribe(&upcast!(a.clone() => dyn Handler<Add>));
+	demux.subscribe(&upcast!(b.clone() => dyn Handler<Add>));
+
+	let (stalled, is_stalled) = mpsc::channel();
+	let (gate, wait) = mpsc::channel();
+	b.tell(Stall(stalled, wait));
+	is_stalled.recv().unwrap();
+
+	let responses: HashMap<_, _> = block_on(demux.broadcast(Add(2), Some(Duration::from_millis(20)))).into_iter().map(|(subscriber, response)| {
+		(subscriber.id(), response)
+	}).collect();
+
+	assert_eq!(responses, HashMap::from([(a.id(), Ok(3)), (b.id(), Err(SendError::Timeout))]));
+	gate.send(()).unwrap();
+	close(a, a_processor);
+	close(b, b_processor)
+}
+
+#[test]
+fn no_subscriber() {
+	let demux = Demux::<Add>::new();
+	assert!(block_on(demux.broadcast(Add(1), Some(Duration::from_millis(20)))).is_empty());
+
+	let (a, a_processor) = spawn(1);
+	let mut demux = Demux::new();
+	demux.subscribe(&upcast!(a.clone() => dyn Handler<Add>));
+	close(a, a_processor);
+	assert!(block_on(demux.broadcast(Add(1), None)).is_empty());
+}
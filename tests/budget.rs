@@ -0,0 +1,102 @@
+//! Events posted per processor poll.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::{mpsc, Arc};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context as TaskContext, Poll};
+use futures::FutureExt;
+use futures::task::{ArcWake, waker};
+use bottle::{Output, Remote, Handler, EventQueue, Summary, DEFAULT_BUDGET};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Record the given value.
+struct Record(u32);
+
+impl bottle::Event for Record {
+	type Response = ();
+}
+
+struct Actor {
+	log: mpsc::Sender<u32>
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Record> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Record(value): Record) -> Output<'a, ()> {
+		self.log.send(value).unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Record> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Record(value): Record) -> Output<'a, ()> {
+		self.log.send(value).unwrap();
+		Output::Now(())
+	}
+}
+
+/// Counts its wake-ups.
+#[derive(Default)]
+struct Counter(AtomicUsize);
+
+impl ArcWake for Counter {
+	fn wake_by_ref(counter: &Arc<Self>) {
+		counter.0.fetch_add(1, Ordering::SeqCst);
+	}
+}
+
+#[test]
+fn drains_the_queue() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor { log });
+	for value in 0..10 {
+		actor.tell(Record(value));
+	}
+
+	let mut processor = queue.process();
+	assert_eq!(processor.budget(), DEFAULT_BUDGET);
+	assert!((&mut processor).now_or_never().is_none());
+	assert_eq!(records.try_iter().count(), 10);
+
+	processor.reference().close();
+	assert_eq!(processor.now_or_never(), Some(Summary { events: 10 }));
+}
+
+#[test]
+fn yields_once_the_budget_is_exhausted() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor { log });
+	for value in 0..5 {
+		actor.tell(Record(value));
+	}
+
+	let mut processor = queue.process();
+	processor.set_budget(2);
+	processor.reference().close();
+
+	let counter = Arc::new(Counter::default());
+	let waker = waker(counter.clone());
+	let mut ctx = TaskContext::from_waker(&waker);
+	for (polls, handled) in [(1, vec![0, 1]), (2, vec![2, 3])] {
+		// The processor requests to be polled again, so that the remaining events are posted.
+		assert!(processor.poll_unpin(&mut ctx).is_pending());
+		assert_eq!(records.try_iter().collect::<Vec<_>>(), handled);
+		assert_eq!(counter.0.load(Ordering::SeqCst), polls);
+	}
+
+	assert_eq!(processor.poll_unpin(&mut ctx), Poll::Ready(Summary { events: 5 }));
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), [4]);
+	assert_eq!(counter.0.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+#[should_panic(expected = "budget must be positive")]
+fn zero_budget() {
+	EventQueue::new().process().set_budget(0)
+}
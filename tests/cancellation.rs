@@ -0,0 +1,149 @@
+//! Dropping response futures cancels their events, unless they are detached.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::mpsc;
+use std::time::Duration;
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Record the given value.
+struct Record(u32);
+
+impl bottle::Event for Record {
+	type Response = ();
+}
+
+/// Wait for the gate, then report whether the event has been cancelled.
+struct Wait(oneshot::Receiver<()>, mpsc::Sender<&'static str>);
+
+impl bottle::Event for Wait {
+	type Response = ();
+}
+
+struct Actor {
+	log: mpsc::Sender<u32>
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Record> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Record(value): Record) -> Output<'a, ()> {
+		self.log.send(value).unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Wait> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Wait(gate, signals): Wait) -> Output<'a, ()> {
+		signals.send("started").unwrap();
+		async move {
+			gate.await.unwrap();
+			signals.send(if self.is_cancelled() { "cancelled" } else { "running" }).unwrap();
+		}.into()
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Record> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Record(value): Record) -> Output<'a, ()> {
+		self.log.send(value).unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Wait> for Actor {
+	fn handle<'a>(&'a mut self, ctx: &'a mut Context<Self>, Wait(gate, signals): Wait) -> Output<'a, ()> {
+		signals.send("started").unwrap();
+		async move {
+			gate.await.unwrap();
+			signals.send(if ctx.is_cancelled() { "cancelled" } else { "running" }).unwrap();
+		}.into()
+	}
+}
+
+#[test]
+fn dropped_event_is_skipped() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor { log });
+
+	let first = actor.send(Record(1));
+	let second = actor.send(Record(2));
+	let third = actor.ask(Record(3));
+	std::mem::drop(first);
+	std::mem::drop(third);
+
+	queue.reference().close();
+	queue.run_blocking();
+
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), [2]);
+	block_on(second);
+}
+
+#[test]
+fn detached_event_is_handled() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor { log });
+
+	actor.send(Record(1)).detach();
+	actor.ask(Record(2)).detach();
+	std::mem::drop(actor.send(Record(3)));
+
+	queue.reference().close();
+	queue.run_blocking();
+
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), [1, 2]);
+}
+
+#[test]
+fn dropped_timeout_is_cancelled() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor { log });
+
+	std::mem::drop(actor.ask(Record(1)).timeout(Duration::from_secs(60)));
+	let second = actor.ask(Record(2)).timeout(Duration::from_secs(60));
+
+	queue.reference().close();
+	queue.run_blocking();
+
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), [2]);
+	assert_eq!(block_on(second), Ok(()));
+}
+
+#[test]
+fn running_handler_observes_cancellation() {
+	let (log, _records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor { log });
+	let processor = queue.reference();
+	let thread = std::thread::spawn(move || queue.run_blocking());
+
+	for cancel in [false, true] {
+		let (open, gate) = oneshot::channel();
+		let (signals, events) = mpsc::channel();
+		let response = actor.send(Wait(gate, signals));
+
+		assert_eq!(events.recv_timeout(Duration::from_secs(5)), Ok("started"));
+		if cancel {
+			std::mem::drop(response)
+		} else {
+			response.detach()
+		}
+		open.send(()).unwrap();
+
+		let expected = if cancel { "cancelled" } else { "running" };
+		assert_eq!(events.recv_timeout(Duration::from_secs(5)), Ok(expected));
+	}
+
+	assert_eq!(block_on(actor.ask(Record(0))), Ok(()));
+	processor.close();
+	thread.join().unwrap();
+}
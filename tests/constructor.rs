@@ -0,0 +1,113 @@
+//! Actors constructed in their own thread, with `Remote::from`.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::thread::{self, ThreadId};
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue, SendError};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Get the thread of the actor, and of its constructor.
+struct Threads;
+
+impl bottle::Event for Threads {
+	type Response = (ThreadId, ThreadId);
+}
+
+/// Checks if the given actor can be reached with a local pointer from the handler.
+struct IsLocal(Remote<Actor>);
+
+impl bottle::Event for IsLocal {
+	type Response = bool;
+}
+
+struct Actor {
+	constructed_in: ThreadId
+}
+
+impl Actor {
+	fn new() -> Actor {
+		Actor {
+			constructed_in: thread::current().id()
+		}
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Threads> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Threads) -> Output<'a, (ThreadId, ThreadId)> {
+		Output::Now((thread::current().id(), self.constructed_in))
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Threads> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Threads) -> Output<'a, (ThreadId, ThreadId)> {
+		Output::Now((thread::current().id(), self.constructed_in))
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<IsLocal> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, IsLocal(other): IsLocal) -> Output<'a, bool> {
+		Output::Now(other.local_to(&self).is_some())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<IsLocal> for Actor {
+	fn handle<'a>(&'a mut self, ctx: &'a mut Context<Self>, IsLocal(other): IsLocal) -> Output<'a, bool> {
+		Output::Now(other.local_to(ctx).is_some())
+	}
+}
+
+#[test]
+fn events_wait_for_the_constructor() {
+	let queue = EventQueue::new();
+	let actor = Remote::from(queue.reference(), Actor::new);
+	let threads = actor.ask(Threads);
+
+	queue.reference().close();
+	let processor = thread::spawn(move || {
+		queue.run_blocking();
+		thread::current().id()
+	});
+
+	let processor = processor.join().unwrap();
+	assert_eq!(block_on(threads), Ok((processor, processor)));
+}
+
+#[test]
+fn constructor_panic_fails_the_actor() {
+	let queue = EventQueue::new();
+	let actor = Remote::from(queue.reference(), || -> Actor { panic!("constructor") });
+	let threads = actor.ask(Threads);
+
+	queue.reference().close();
+	queue.run_blocking();
+
+	assert_eq!(block_on(threads), Err(SendError::HandlerPanicked));
+	assert!(actor.is_failed());
+	assert!(actor.is_stopped());
+	assert_eq!(block_on(actor.ask(Threads)), Err(SendError::ActorStopped));
+}
+
+#[test]
+fn local_to_same_queue() {
+	let queue = EventQueue::new();
+	let other_queue = EventQueue::new();
+	let actor = Remote::from(queue.reference(), Actor::new);
+	let neighbour = Remote::new(queue.reference(), Actor::new());
+	let stranger = Remote::new(other_queue.reference(), Actor::new());
+
+	let same = actor.ask(IsLocal(neighbour.clone()));
+	let other = actor.ask(IsLocal(stranger));
+
+	queue.reference().close();
+	queue.run_blocking();
+
+	assert_eq!(block_on(same), Ok(true));
+	assert_eq!(block_on(other), Ok(false));
+}
@@ -0,0 +1,154 @@
+//! Detection of busy actors awaiting each other, under each policy, in debug builds.
+#![cfg(debug_assertions)]
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::Mutex;
+use futures::FutureExt;
+use bottle::{Output, Remote, Handler, EventQueue, SendError, DeadlockPolicy, set_deadlock_policy};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Forward the event along the path, awaiting the response of the next actor.
+struct Forward(Vec<Remote<Actor>>);
+
+impl bottle::Event for Forward {
+	type Response = Result<(), SendError>;
+}
+
+struct Actor;
+
+#[cfg(feature = "nightly")]
+impl Handler<Forward> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Forward(mut path): Forward) -> Output<'a, Result<(), SendError>> {
+		if path.is_empty() {
+			Output::Now(Ok(()))
+		} else {
+			async move {
+				let next = path.remove(0);
+				next.ask(Forward(path)).await.and_then(|result| result)
+			}.into()
+		}
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Forward> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Forward(mut path): Forward) -> Output<'a, Result<(), SendError>> {
+		if path.is_empty() {
+			Output::Now(Ok(()))
+		} else {
+			async move {
+				let next = path.remove(0);
+				next.ask(Forward(path)).await.and_then(|result| result)
+			}.into()
+		}
+	}
+}
+
+/// The deadlock policy is global to the process.
+static POLICY: Mutex<()> = Mutex::new(());
+
+/// Send an event around the cycle of actors, starting and ending with the first one.
+///
+/// Returns `None` if the event is never handled.
+fn forward(policy: DeadlockPolicy, cycle: &[Remote<Actor>], queue: EventQueue) -> Option<Result<(), SendError>> {
+	let _policy = POLICY.lock().unwrap_or_else(|e| e.into_inner());
+	set_deadlock_policy(policy);
+
+	let mut path = cycle[1..].to_vec();
+	path.push(cycle[0].clone());
+	let mut response = cycle[0].ask(Forward(path));
+
+	let mut processor = queue.process();
+	for _ in 0..100 {
+		assert!((&mut processor).now_or_never().is_none());
+		if let Some(result) = (&mut response).now_or_never() {
+			return Some(result.and_then(|result| result))
+		}
+	}
+
+	None
+}
+
+/// A cycle of two actors, and an actor awaiting itself.
+fn cycles() -> Vec<(Vec<Remote<Actor>>, EventQueue)> {
+	let two = EventQueue::new();
+	let one = EventQueue::new();
+	vec![
+		(vec![Remote::new(two.reference(), Actor), Remote::new(two.reference(), Actor)], two),
+		(vec![Remote::new(one.reference(), Actor)], one)
+	]
+}
+
+#[test]
+fn fail() {
+	for (cycle, queue) in cycles() {
+		assert_eq!(forward(DeadlockPolicy::Fail, &cycle, queue), Some(Err(SendError::Deadlock)));
+		assert!(cycle.iter().all(|actor| !actor.is_failed()));
+	}
+}
+
+#[test]
+fn panic() {
+	for (cycle, queue) in cycles() {
+		assert_eq!(forward(DeadlockPolicy::Panic, &cycle, queue), Some(Err(SendError::HandlerPanicked)));
+
+		// The actor closing the cycle fails.
+		assert!(cycle.last().unwrap().is_failed());
+	}
+}
+
+/// Records the logged messages.
+struct Recorder(Mutex<Vec<String>>);
+
+impl log::Log for Recorder {
+	fn enabled(&self, _metadata: &log::Metadata) -> bool {
+		true
+	}
+
+	fn log(&self, record: &log::Record) {
+		self.0.lock().unwrap().push(format!("{}: {}", record.level(), record.args()))
+	}
+
+	fn flush(&self) {}
+}
+
+static RECORDER: Recorder = Recorder(Mutex::new(Vec::new()));
+
+#[test]
+fn log() {
+	log::set_logger(&RECORDER).unwrap();
+	log::set_max_level(log::LevelFilter::Error);
+
+	for (cycle, queue) in cycles() {
+		assert_eq!(forward(DeadlockPolicy::Log, &cycle, queue), None);
+
+		let messages = std::mem::take(&mut *RECORDER.0.lock().unwrap());
+		assert_eq!(messages.len(), 1);
+		assert!(messages[0].starts_with("ERROR: deadlock detected:"));
+		assert_eq!(messages[0].lines().count(), cycle.len() + 1);
+	}
+}
+
+#[test]
+fn ignore() {
+	for (cycle, queue) in cycles() {
+		assert_eq!(forward(DeadlockPolicy::Ignore, &cycle, queue), None);
+	}
+}
+
+#[test]
+fn no_cycle() {
+	let queue = EventQueue::new();
+	let first = Remote::new(queue.reference(), Actor);
+	let second = Remote::new(queue.reference(), Actor);
+	let _policy = POLICY.lock().unwrap_or_else(|e| e.into_inner());
+	set_deadlock_policy(DeadlockPolicy::Fail);
+
+	let response = first.ask(Forward(vec![second]));
+	queue.reference().close();
+	queue.run_blocking();
+	assert_eq!(response.now_or_never(), Some(Ok(Ok(()))));
+}
@@ -0,0 +1,120 @@
+//! Events and handlers derived with the `derive` feature.
+#![cfg(all(feature = "derive", not(feature = "nightly")))]
+
+use std::future::Future;
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use bottle::{Context, Remote, EventQueue, Event, Priority};
+
+#[derive(bottle::Event)]
+#[event(response = u32)]
+struct Get;
+
+#[derive(bottle::Event)]
+#[event(priority = Low)]
+struct Set(u32);
+
+#[derive(bottle::Event)]
+#[event(response = u32)]
+struct Increment;
+
+#[derive(bottle::Event)]
+#[event(response = bool)]
+struct IsSelf(Remote<Counter>);
+
+/// Add the value of the given counter.
+#[derive(bottle::Event)]
+#[event(response = u32)]
+struct Add(Remote<Counter>);
+
+/// Wait for the gate reentrantly, then read the counter.
+#[derive(bottle::Event)]
+#[event(response = u32)]
+struct Wait(oneshot::Receiver<()>);
+
+struct Counter {
+	count: u32
+}
+
+#[bottle::actor]
+impl Counter {
+	#[handle]
+	fn get(&self, _event: Get) -> u32 {
+		self.count
+	}
+
+	// Routed like `&mut self`.
+	#[handle]
+	#[allow(clippy::needless_arbitrary_self_type)]
+	fn set(self: &mut Self, Set(count): Set) {
+		self.count = count
+	}
+
+	#[handle]
+	fn increment(&mut self, _event: Increment) -> u32 {
+		self.count += 1;
+		self.count
+	}
+
+	#[handle]
+	fn is_self(&mut self, ctx: &mut Context<Self>, IsSelf(remote): IsSelf) -> bool {
+		ctx.as_remote() == remote
+	}
+
+	#[handle]
+	async fn add(&mut self, Add(other): Add) -> u32 {
+		self.count += other.send(Get).await;
+		self.count
+	}
+
+	#[handle(reentrant)]
+	fn wait(&mut self, ctx: &mut Context<Self>, Wait(gate): Wait) -> impl 'static + Future<Output = u32> {
+		let this: Remote<Counter> = ctx.as_remote();
+		async move {
+			let _ = gate.await;
+			this.send(Get).await
+		}
+	}
+}
+
+#[test]
+fn event() {
+	assert_eq!(<Get as Event>::PRIORITY, Priority::Normal);
+	assert_eq!(<Set as Event>::PRIORITY, Priority::Low);
+}
+
+#[test]
+fn handlers() {
+	let queue = EventQueue::new();
+	let a = Remote::new(queue.reference(), Counter { count: 0 });
+	let b = Remote::new(queue.reference(), Counter { count: 40 });
+
+	let responses = vec![a.send(Increment), a.send(Add(b.clone())), a.send(Get)];
+	let set = a.send(Set(1));
+	let is_self = vec![a.send(IsSelf(a.clone())), a.send(IsSelf(b))];
+
+	queue.reference().close();
+	queue.run_blocking();
+	assert_eq!(responses.into_iter().map(block_on).collect::<Vec<_>>(), [1, 41, 41]);
+	block_on(set);
+	assert_eq!(is_self.into_iter().map(block_on).collect::<Vec<_>>(), [true, false]);
+}
+
+#[test]
+fn reentrant() {
+	let queue = EventQueue::new();
+	let reference = queue.reference();
+	let counter = Remote::new(queue.reference(), Counter { count: 0 });
+	let processor = std::thread::spawn(move || queue.run_blocking());
+
+	let (open, gate) = oneshot::channel();
+	let wait = counter.send(Wait(gate));
+
+	// The counter is not busy while the reentrant future is suspended.
+	assert_eq!(block_on(counter.send(Increment)), 1);
+	open.send(()).unwrap();
+	assert_eq!(block_on(wait), 1);
+
+	reference.close();
+	processor.join().unwrap();
+}
@@ -0,0 +1,130 @@
+//! Discovery of actors by the event types they handle.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue, Registry};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+struct Ping;
+
+impl bottle::Event for Ping {
+	type Response = u32;
+}
+
+struct Pong;
+
+impl bottle::Event for Pong {
+	type Response = ();
+}
+
+struct Plugin(u32);
+
+#[cfg(feature = "nightly")]
+impl Handler<Ping> for Plugin {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Ping) -> Output<'a, u32> {
+		Output::Now(self.0)
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Ping> for Plugin {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Ping) -> Output<'a, u32> {
+		Output::Now(self.0)
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Pong> for Plugin {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Pong) -> Output<'a, ()> {
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Pong> for Plugin {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Pong) -> Output<'a, ()> {
+		Output::Now(())
+	}
+}
+
+/// Another actor type handling the same events.
+struct Other;
+
+#[cfg(feature = "nightly")]
+impl Handler<Ping> for Other {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Ping) -> Output<'a, u32> {
+		Output::Now(42)
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Ping> for Other {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Ping) -> Output<'a, u32> {
+		Output::Now(42)
+	}
+}
+
+#[test]
+fn announce() {
+	let registry = Registry::new();
+	let queue = EventQueue::new();
+	let a = Remote::new(queue.reference(), Plugin(0));
+	let b = Remote::new(queue.reference(), Other);
+	let c = Remote::new(queue.reference(), Plugin(1));
+
+	assert!(registry.handlers::<Ping>().is_empty());
+	registry.announce::<Ping, _>(&a);
+	registry.announce::<Ping, _>(&b);
+	registry.announce::<Ping, _>(&a);
+	registry.announce::<Pong, _>(&c);
+	registry.announce::<Ping, _>(&c);
+
+	// Announcements are independent of the names.
+	assert!(registry.names().is_empty());
+
+	let handlers = registry.handlers::<Ping>();
+	let ids: Vec<_> = handlers.iter().map(Remote::id).collect();
+	assert_eq!(ids, [a.id(), b.id(), c.id()]);
+	assert_eq!(registry.handlers::<Pong>().iter().map(Remote::id).collect::<Vec<_>>(), [c.id()]);
+
+	let responses: Vec<_> = handlers.iter().map(|handler| handler.send(Ping)).collect();
+	queue.reference().close();
+	queue.run_blocking();
+	assert_eq!(responses.into_iter().map(block_on).collect::<Vec<_>>(), [0, 42, 1]);
+}
+
+#[test]
+fn withdraw() {
+	let registry = Registry::new();
+	let queue = EventQueue::new();
+	let a = Remote::new(queue.reference(), Plugin(0));
+	let b = Remote::new(queue.reference(), Plugin(1));
+	registry.announce::<Ping, _>(&a);
+	registry.announce::<Ping, _>(&b);
+	registry.announce::<Pong, _>(&a);
+
+	assert!(registry.withdraw::<Ping, _>(&a));
+	assert!(!registry.withdraw::<Ping, _>(&a));
+	assert!(!registry.withdraw::<Pong, _>(&b));
+	assert_eq!(registry.handlers::<Ping>().iter().map(Remote::id).collect::<Vec<_>>(), [b.id()]);
+	assert_eq!(registry.handlers::<Pong>().iter().map(Remote::id).collect::<Vec<_>>(), [a.id()]);
+}
+
+#[test]
+fn stopped_actors_are_withdrawn() {
+	let registry = Registry::new();
+	let queue = EventQueue::new();
+	let a = Remote::new(queue.reference(), Plugin(0));
+	let b = Remote::new(queue.reference(), Plugin(1));
+	let c = Remote::new(queue.reference(), Plugin(2));
+	registry.announce::<Ping, _>(&a);
+	registry.announce::<Ping, _>(&b);
+	registry.announce::<Ping, _>(&c);
+
+	a.stop();
+	std::mem::drop(b);
+	assert_eq!(registry.handlers::<Ping>().iter().map(Remote::id).collect::<Vec<_>>(), [c.id()]);
+}
@@ -0,0 +1,105 @@
+//! Emitters derived with the `derive` feature.
+#![cfg(feature = "derive")]
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::mpsc;
+use futures::executor::block_on;
+use static_assertions::assert_impl_all;
+use bottle::{Remote, EventQueue, Demux, Emitter};
+use bottle as renamed;
+
+#[derive(Clone, bottle::Event)]
+struct Value<T: 'static + Send>(T);
+
+#[derive(Clone, bottle::Event)]
+struct Changed;
+
+/// Emit the given value, and its successor to the skipped field.
+#[derive(bottle::Event)]
+struct Emit<T: 'static + Send>(T);
+
+#[derive(bottle::Emitter)]
+struct Source<T: 'static + Send + Clone> {
+	values: Demux<Value<T>>,
+
+	/// Has the same event type as `values`, but is not part of the emitter.
+	#[emitter(skip)]
+	mirror: Demux<Value<T>>,
+
+	#[emitter(name = changes)]
+	changed: Demux<Changed>
+}
+
+impl<T: 'static + Send + Clone> Source<T> {
+	fn new() -> Source<T> {
+		Source {
+			values: Demux::new(),
+			mirror: Demux::new(),
+			changed: Demux::new()
+		}
+	}
+}
+
+#[bottle::actor]
+impl<T: 'static + Send + Clone + std::ops::Add<Output = T> + From<u8>> Source<T> {
+	#[handle]
+	fn emit(&mut self, Emit(value): Emit<T>) {
+		self.values.send(Value(value.clone()));
+		self.mirror.send(Value(value + T::from(1)));
+		self.changed.send(Changed);
+	}
+}
+
+struct Listener {
+	log: mpsc::Sender<String>
+}
+
+#[bottle::actor]
+impl Listener {
+	#[handle]
+	fn value(&mut self, Value(value): Value<u32>) {
+		self.log.send(format!("value {}", value)).unwrap()
+	}
+
+	#[handle]
+	fn changed(&mut self, _event: Changed) {
+		self.log.send("changed".to_string()).unwrap()
+	}
+}
+
+assert_impl_all!(Source<u32>: Emitter<Value<u32>>, Emitter<Changed>);
+
+#[derive(bottle::Event)]
+#[event(crate = renamed)]
+struct Renamed;
+
+#[derive(renamed::Emitter)]
+#[emitter(crate = renamed)]
+struct RenamedSource {
+	renamed: Demux<Renamed>
+}
+
+assert_impl_all!(RenamedSource: Emitter<Renamed>);
+
+#[test]
+fn generic_emitter() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let source = Remote::new(queue.reference(), Source::<u32>::new());
+	let listener = Remote::new(queue.reference(), Listener { log });
+
+	let subscriptions = vec![
+		source.subscribe_values(&listener),
+		source.subscribe_changes(&listener),
+		source.subscribe_changes(&listener)
+	];
+	source.send(Emit(1)).detach();
+	let unsubscribed = source.unsubscribe_changes(&listener);
+	source.send(Emit(2)).detach();
+
+	queue.reference().close();
+	queue.run_blocking();
+	assert_eq!(subscriptions.into_iter().map(block_on).collect::<Vec<_>>(), [true, true, false]);
+	assert!(block_on(unsubscribed));
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), ["value 1", "changed", "value 2"]);
+}
@@ -0,0 +1,80 @@
+//! Processing a queue with an existing executor.
+#![cfg(any(feature = "tokio", feature = "async-std"))]
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::time::Duration;
+use bottle::{Output, Remote, Handler, EventQueue, Summary, ThreadLocal};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Add to the actor counter, after waiting for the given duration.
+struct Add(u32, Duration);
+
+impl bottle::Event for Add {
+	type Response = u32;
+}
+
+struct Counter(u32);
+
+#[cfg(feature = "nightly")]
+impl Handler<Add> for Counter {
+	fn handle<'a>(mut self: Receiver<'a, Self>, Add(value, delay): Add) -> Output<'a, u32> {
+		let sleep = self.queue().sleep(delay);
+		async move {
+			sleep.await;
+			self.0 += value;
+			self.0
+		}.into()
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Add> for Counter {
+	fn handle<'a>(&'a mut self, ctx: &'a mut Context<Self>, Add(value, delay): Add) -> Output<'a, u32> {
+		let sleep = ctx.queue().sleep(delay);
+		async move {
+			sleep.await;
+			self.0 += value;
+			self.0
+		}.into()
+	}
+}
+
+#[cfg(feature = "tokio")]
+#[test]
+fn tokio() {
+	let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+	let local = tokio::task::LocalSet::new();
+
+	local.block_on(&runtime, async {
+		let queue = EventQueue::new();
+		let reference = queue.reference();
+		let counter = Remote::new(queue.reference(), Counter(0));
+		let processor = queue.spawn_tokio();
+
+		assert_eq!(counter.send(Add(1, Duration::from_millis(10))).await, 1);
+		assert_eq!(counter.send(Add(2, Duration::ZERO)).await, 3);
+
+		reference.close();
+		assert_eq!(processor.await.unwrap(), Summary { events: 2 });
+	})
+}
+
+#[cfg(feature = "async-std")]
+#[test]
+fn async_std() {
+	async_std::task::block_on(async {
+		let queue = EventQueue::new();
+		let reference = queue.reference();
+		let counter = Remote::new(queue.reference(), Counter(0));
+		let processor = queue.spawn_async_std();
+
+		assert_eq!(counter.send(Add(1, Duration::from_millis(10))).await, 1);
+		assert_eq!(counter.send(Add(2, Duration::ZERO)).await, 3);
+
+		reference.close();
+		assert_eq!(processor.await, Summary { events: 2 });
+	})
+}
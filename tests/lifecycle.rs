@@ -0,0 +1,220 @@
+//! Lifecycle hooks, stopping actors and weak pointers.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::mpsc;
+use std::thread::{self, ThreadId};
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue, Lifecycle, SendError};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+struct Ping;
+
+impl bottle::Event for Ping {
+	type Response = ();
+}
+
+/// Stop the actor from its handler.
+struct Stop;
+
+impl bottle::Event for Stop {
+	type Response = ();
+}
+
+struct Actor {
+	log: mpsc::Sender<(&'static str, ThreadId)>
+}
+
+impl Actor {
+	fn record(&self, step: &'static str) {
+		self.log.send((step, thread::current().id())).unwrap()
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Lifecycle for Actor {
+	fn started<'a>(self: Receiver<'a, Self>) -> Output<'a, ()> {
+		self.record("started");
+		Output::Now(())
+	}
+
+	fn stopping<'a>(self: Receiver<'a, Self>) -> Output<'a, ()> {
+		async move {
+			self.record("stopping");
+		}.into()
+	}
+
+	fn stopped<'a>(self: Receiver<'a, Self>) -> Output<'a, ()> {
+		self.record("stopped");
+		Output::Now(())
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Ping> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Ping) -> Output<'a, ()> {
+		self.record("ping");
+		Output::Now(())
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Stop> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Stop) -> Output<'a, ()> {
+		self.stop();
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Lifecycle for Actor {
+	fn started<'a>(&'a mut self, _ctx: &'a mut Context<Self>) -> Output<'a, ()> {
+		self.record("started");
+		Output::Now(())
+	}
+
+	fn stopping<'a>(&'a mut self, _ctx: &'a mut Context<Self>) -> Output<'a, ()> {
+		async move {
+			self.record("stopping");
+		}.into()
+	}
+
+	fn stopped<'a>(&'a mut self, _ctx: &'a mut Context<Self>) -> Output<'a, ()> {
+		self.record("stopped");
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Ping> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Ping) -> Output<'a, ()> {
+		self.record("ping");
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Stop> for Actor {
+	fn handle<'a>(&'a mut self, ctx: &'a mut Context<Self>, _event: Stop) -> Output<'a, ()> {
+		ctx.stop();
+		Output::Now(())
+	}
+}
+
+impl Drop for Actor {
+	fn drop(&mut self) {
+		self.record("dropped")
+	}
+}
+
+/// Process the queue in another thread, and return the log of the actor along with the
+/// processor thread.
+fn run(queue: EventQueue, records: mpsc::Receiver<(&'static str, ThreadId)>) -> (Vec<&'static str>, ThreadId) {
+	queue.reference().close();
+	let processor = thread::spawn(move || {
+		queue.run_blocking();
+		thread::current().id()
+	}).join().unwrap();
+
+	let log = records.try_iter().map(|(step, thread)| {
+		assert_eq!(thread, processor);
+		step
+	}).collect();
+
+	(log, processor)
+}
+
+#[test]
+fn hooks_order() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::spawn(queue.reference(), Actor { log });
+	let ping = actor.ask(Ping);
+	std::mem::drop(actor);
+
+	let (log, _) = run(queue, records);
+	assert_eq!(log, ["started", "ping", "stopping", "stopped", "dropped"]);
+	assert_eq!(block_on(ping), Ok(()));
+}
+
+#[test]
+fn stop_rejects_events() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::spawn(queue.reference(), Actor { log });
+	let ping = actor.ask(Ping);
+	actor.stop();
+
+	assert!(actor.is_stopped());
+	assert_eq!(block_on(actor.ask(Ping)), Err(SendError::ActorStopped));
+	assert_eq!(actor.try_send(Ping).err(), Some(SendError::ActorStopped));
+
+	let (log, _) = run(queue, records);
+	assert_eq!(log, ["started", "ping", "stopping", "stopped", "dropped"]);
+	assert_eq!(block_on(ping), Ok(()));
+	assert!(!actor.is_failed());
+}
+
+#[test]
+fn stop_from_handler() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::spawn(queue.reference(), Actor { log });
+	let stop = actor.ask(Stop);
+	let ping = actor.ask(Ping);
+
+	let (log, _) = run(queue, records);
+	assert_eq!(log, ["started", "ping", "stopping", "stopped", "dropped"]);
+	assert_eq!(block_on(stop), Ok(()));
+	assert_eq!(block_on(ping), Ok(()));
+	assert_eq!(block_on(actor.ask(Ping)), Err(SendError::ActorStopped));
+}
+
+#[test]
+fn weak_upgrade() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor { log });
+	let weak = actor.downgrade();
+
+	let upgraded = weak.upgrade().unwrap();
+	std::mem::drop(actor);
+	assert!(!weak.is_stopped());
+	std::mem::drop(upgraded);
+
+	// The last handle is gone: the actor cannot be revived.
+	assert!(weak.is_stopped());
+	assert!(weak.upgrade().is_none());
+	assert!(weak.send(Ping).is_none());
+
+	let (log, _) = run(queue, records);
+	assert_eq!(log, ["dropped"]);
+	assert!(weak.upgrade().is_none());
+}
+
+#[test]
+fn concurrent_upgrade_and_drop() {
+	for _ in 0..200 {
+		let (log, records) = mpsc::channel();
+		let queue = EventQueue::new();
+		let actor = Remote::spawn(queue.reference(), Actor { log });
+		let weak = actor.downgrade();
+
+		let upgrader = thread::spawn(move || {
+			while let Some(actor) = weak.upgrade() {
+				// The upgraded handle keeps the actor alive: it cannot be revived once the last
+				// handle is gone.
+				assert!(!actor.is_stopped());
+			}
+		});
+
+		std::mem::drop(actor);
+		upgrader.join().unwrap();
+
+		// Whatever the interleaving, the actor is stopped exactly once.
+		let (log, _) = run(queue, records);
+		assert_eq!(log, ["started", "stopping", "stopped", "dropped"]);
+	}
+}
@@ -0,0 +1,125 @@
+//! Bounded mailboxes and their overflow policies.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::Arc;
+use std::task::Context as TaskContext;
+use futures::FutureExt;
+use futures::task::{ArcWake, waker};
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue, Capacity, Overflow, SendError};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+struct Echo(u32);
+
+impl bottle::Event for Echo {
+	type Response = u32;
+}
+
+struct Actor;
+
+#[cfg(feature = "nightly")]
+impl Handler<Echo> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Echo(value): Echo) -> Output<'a, u32> {
+		Output::Now(value)
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Echo> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Echo(value): Echo) -> Output<'a, u32> {
+		Output::Now(value)
+	}
+}
+
+struct Noop;
+
+impl ArcWake for Noop {
+	fn wake_by_ref(_: &Arc<Self>) {}
+}
+
+#[test]
+fn wait_for_room() {
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor);
+	actor.set_capacity(Some(Capacity::new(1, Overflow::Wait)));
+
+	let first = actor.ask(Echo(1));
+	let mut second = actor.ask(Echo(2));
+	assert_eq!((&mut second).now_or_never(), None);
+	assert_eq!(actor.try_send(Echo(3)).err(), Some(SendError::Full));
+
+	let reference = queue.reference();
+	let processor = std::thread::spawn(move || queue.run_blocking());
+	assert_eq!(block_on(first), Ok(1));
+	assert_eq!(block_on(second), Ok(2));
+
+	reference.close();
+	processor.join().unwrap();
+}
+
+#[test]
+fn waiting_sender_registers_its_waker_once() {
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor);
+	actor.set_capacity(Some(Capacity::new(1, Overflow::Wait)));
+
+	let _first = actor.ask(Echo(1));
+	let mut second = actor.ask(Echo(2));
+
+	let wake = Arc::new(Noop);
+	let waker = waker(wake.clone());
+	let mut ctx = TaskContext::from_waker(&waker);
+	for _ in 0..100 {
+		assert!(second.poll_unpin(&mut ctx).is_pending());
+	}
+
+	// One reference for `wake`, one for `waker`, and one for the mailbox.
+	assert_eq!(Arc::strong_count(&wake), 3);
+}
+
+#[test]
+fn drop_newest() {
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor);
+	actor.set_capacity(Some(Capacity::new(1, Overflow::DropNewest)));
+
+	let first = actor.ask(Echo(1));
+	let second = actor.ask(Echo(2));
+
+	queue.reference().close();
+	queue.run_blocking();
+	assert_eq!(block_on(first), Ok(1));
+	assert_eq!(block_on(second), Err(SendError::Full));
+}
+
+#[test]
+fn drop_oldest() {
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor);
+	actor.set_capacity(Some(Capacity::new(1, Overflow::DropOldest)));
+
+	let first = actor.ask(Echo(1));
+	let second = actor.ask(Echo(2));
+
+	queue.reference().close();
+	queue.run_blocking();
+	assert_eq!(block_on(first), Err(SendError::Full));
+	assert_eq!(block_on(second), Ok(2));
+}
+
+#[test]
+fn queue_capacity() {
+	let queue = EventQueue::with_capacity(Capacity::new(2, Overflow::DropNewest));
+	let a = Remote::new(queue.reference(), Actor);
+	let b = Remote::new(queue.reference(), Actor);
+
+	let responses = vec![a.ask(Echo(1)), b.ask(Echo(2)), a.ask(Echo(3))];
+	queue.reference().close();
+	queue.run_blocking();
+
+	let responses: Vec<_> = responses.into_iter().map(block_on).collect();
+	assert_eq!(responses, [Ok(1), Ok(2), Err(SendError::Full)]);
+}
@@ -0,0 +1,162 @@
+//! A panicking handler fails its actor only.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::mpsc;
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue, Lifecycle, SendError};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+struct Ping;
+
+impl bottle::Event for Ping {
+	type Response = ();
+}
+
+/// Panic right away.
+struct Panic;
+
+impl bottle::Event for Panic {
+	type Response = ();
+}
+
+/// Panic once the gate is opened.
+struct PanicLater(oneshot::Receiver<()>);
+
+impl bottle::Event for PanicLater {
+	type Response = ();
+}
+
+struct Actor {
+	log: mpsc::Sender<&'static str>
+}
+
+#[cfg(feature = "nightly")]
+impl Lifecycle for Actor {
+	fn stopping<'a>(self: Receiver<'a, Self>) -> Output<'a, ()> {
+		self.log.send("stopping").unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Ping> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Ping) -> Output<'a, ()> {
+		self.log.send("ping").unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Panic> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Panic) -> Output<'a, ()> {
+		panic!("handler")
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<PanicLater> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, PanicLater(gate): PanicLater) -> Output<'a, ()> {
+		async move {
+			let _ = gate.await;
+			panic!("handler")
+		}.into()
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Lifecycle for Actor {
+	fn stopping<'a>(&'a mut self, _ctx: &'a mut Context<Self>) -> Output<'a, ()> {
+		self.log.send("stopping").unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Ping> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Ping) -> Output<'a, ()> {
+		self.log.send("ping").unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Panic> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Panic) -> Output<'a, ()> {
+		panic!("handler")
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<PanicLater> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, PanicLater(gate): PanicLater) -> Output<'a, ()> {
+		async move {
+			let _ = gate.await;
+			panic!("handler")
+		}.into()
+	}
+}
+
+impl Drop for Actor {
+	fn drop(&mut self) {
+		self.log.send("dropped").unwrap()
+	}
+}
+
+#[test]
+fn sync_handler() {
+	let (log, records) = mpsc::channel();
+	let (other_log, other_records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::spawn(queue.reference(), Actor { log });
+	let other = Remote::spawn(queue.reference(), Actor { log: other_log });
+
+	let responses = vec![actor.ask(Ping), actor.ask(Panic), actor.ask(Ping), other.ask(Ping)];
+	queue.reference().close();
+	queue.run_blocking();
+
+	let responses: Vec<_> = responses.into_iter().map(block_on).collect();
+	assert_eq!(responses, [Ok(()), Err(SendError::HandlerPanicked), Err(SendError::ActorStopped), Ok(())]);
+	assert!(actor.is_failed());
+	assert!(!other.is_failed());
+
+	// The state of the failed actor is dropped without calling the lifecycle hooks.
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), ["ping", "dropped"]);
+	assert_eq!(other_records.try_iter().collect::<Vec<_>>(), ["ping"]);
+	assert_eq!(block_on(actor.ask(Ping)), Err(SendError::ActorStopped));
+}
+
+#[test]
+fn async_handler() {
+	let (log, records) = mpsc::channel();
+	let (other_log, _other_records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let reference = queue.reference();
+	let actor = Remote::new(queue.reference(), Actor { log });
+	let other = Remote::new(queue.reference(), Actor { log: other_log });
+	let processor = std::thread::spawn(move || queue.run_blocking());
+
+	let (open, gate) = oneshot::channel();
+	let panic = actor.ask(PanicLater(gate));
+	let waiting = actor.ask(Ping);
+
+	// Once the other actor has handled its event, the ping waits in the inbox of the busy actor.
+	block_on(other.ask(Ping)).unwrap();
+	open.send(()).unwrap();
+
+	assert_eq!(block_on(panic), Err(SendError::HandlerPanicked));
+	assert_eq!(block_on(waiting), Err(SendError::ActorStopped));
+	assert_eq!(block_on(actor.ask(Ping)), Err(SendError::ActorStopped));
+	assert!(actor.is_failed());
+
+	// The queue keeps processing the events of the other actors.
+	assert_eq!(block_on(other.ask(Ping)), Ok(()));
+	assert!(!other.is_failed());
+
+	reference.close();
+	processor.join().unwrap();
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), ["dropped"]);
+}
@@ -0,0 +1,119 @@
+//! Events of higher priority overtake the others, in the queue and in the inboxes.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::mpsc;
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue, Priority};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Record the given value.
+struct Record(u32);
+
+impl bottle::Event for Record {
+	type Response = ();
+}
+
+/// Keep the actor busy until the gate is opened.
+struct Busy(oneshot::Receiver<()>);
+
+impl bottle::Event for Busy {
+	type Response = ();
+}
+
+struct Actor {
+	log: mpsc::Sender<u32>
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Record> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Record(value): Record) -> Output<'a, ()> {
+		self.log.send(value).unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Record> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Record(value): Record) -> Output<'a, ()> {
+		self.log.send(value).unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Busy> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Busy(gate): Busy) -> Output<'a, ()> {
+		async move {
+			let _ = gate.await;
+		}.into()
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Busy> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Busy(gate): Busy) -> Output<'a, ()> {
+		async move {
+			let _ = gate.await;
+		}.into()
+	}
+}
+
+const EVENTS: [(u32, Priority); 5] = [
+	(1, Priority::Low),
+	(2, Priority::Normal),
+	(3, Priority::High),
+	(4, Priority::Normal),
+	(5, Priority::High)
+];
+
+#[test]
+fn queue_order() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor { log });
+
+	for (value, priority) in EVENTS {
+		if value % 2 == 0 {
+			actor.try_send_with_priority(Record(value), priority).unwrap().detach();
+		} else {
+			actor.send_with_priority(Record(value), priority).detach();
+		}
+	}
+
+	queue.reference().close();
+	queue.run_blocking();
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), [3, 5, 2, 4, 1]);
+}
+
+#[test]
+fn inbox_order() {
+	let (log, records) = mpsc::channel();
+	let (probe_log, _probes) = mpsc::channel();
+	let queue = EventQueue::new();
+	let reference = queue.reference();
+	let actor = Remote::new(queue.reference(), Actor { log });
+	let probe = Remote::new(queue.reference(), Actor { log: probe_log });
+	let processor = std::thread::spawn(move || queue.run_blocking());
+
+	let (open, gate) = oneshot::channel();
+	let busy = actor.send(Busy(gate));
+	for (value, priority) in EVENTS {
+		actor.send_with_priority(Record(value), priority).detach();
+
+		// Once the probe is handled, the event has been posted to the inbox of the busy actor.
+		block_on(probe.send_with_priority(Record(0), Priority::Low));
+	}
+
+	assert!(records.try_iter().next().is_none());
+	open.send(()).unwrap();
+	block_on(busy);
+	block_on(actor.send_with_priority(Record(0), Priority::Low));
+
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), [3, 5, 2, 4, 1, 0]);
+	reference.close();
+	processor.join().unwrap();
+}
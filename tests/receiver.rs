@@ -0,0 +1,172 @@
+//! Conversions of `Receiver` into actor pointers.
+//!
+//! These tests are single-threaded so that they can be checked with Miri:
+//! `cargo +nightly miri test --features nightly --test receiver`.
+#![cfg(feature = "nightly")]
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use futures::executor::block_on;
+use bottle::{Output, Receiver, Remote, Handler, EventQueue, ThreadLocal};
+
+/// Checks that the receiver gives back pointers to the given actor.
+struct Identify<T: ?Sized>(Remote<T>);
+
+impl<T: ?Sized> bottle::Event for Identify<T> {
+	type Response = bool;
+}
+
+/// Increment the actor counter after waiting for another actor.
+struct Increment(Option<Remote<dyn Handler<Increment>>>);
+
+impl bottle::Event for Increment {
+	type Response = u64;
+}
+
+struct Small {
+	count: u64
+}
+
+#[repr(align(256))]
+struct Aligned {
+	count: u64
+}
+
+macro_rules! actor {
+	($type:ty) => {
+		impl Handler<Identify<$type>> for $type {
+			fn handle<'a>(self: Receiver<'a, Self>, Identify(remote): Identify<$type>) -> Output<'a, bool> {
+				let local = self.as_local();
+				let same_queue = self.queue() == remote.queue();
+				Output::Now(self.as_remote() == remote && local.as_remote() == remote && same_queue)
+			}
+		}
+
+		impl Handler<Identify<dyn Handler<Increment>>> for $type {
+			fn handle<'a>(self: Receiver<'a, Self>, Identify(remote): Identify<dyn Handler<Increment>>) -> Output<'a, bool> {
+				let this: Remote<dyn Handler<Increment>> = self.as_remote();
+				Output::Now(this == remote)
+			}
+		}
+
+		impl Handler<Increment> for $type {
+			fn handle<'a>(mut self: Receiver<'a, Self>, Increment(other): Increment) -> Output<'a, u64> {
+				async move {
+					if let Some(other) = other {
+						other.send(Increment(None)).await;
+					}
+
+					self.count += 1;
+					self.count
+				}.into()
+			}
+		}
+	};
+}
+
+actor!(Small);
+actor!(Aligned);
+
+/// Actors reached through `dyn Counter` pointers, handling events as trait objects.
+trait Counter {
+	fn count(&self) -> u64;
+}
+
+impl Counter for Small {
+	fn count(&self) -> u64 {
+		self.count
+	}
+}
+
+impl Counter for Aligned {
+	fn count(&self) -> u64 {
+		self.count
+	}
+}
+
+/// Get the count of the actor if the receiver gives back pointers to the given actor.
+struct Count(Remote<dyn Counter>);
+
+impl bottle::Event for Count {
+	type Response = Option<u64>;
+}
+
+impl Handler<Count> for dyn Counter {
+	fn handle<'a>(self: Receiver<'a, Self>, Count(remote): Count) -> Output<'a, Option<u64>> {
+		let local = self.as_local();
+		let is_same = remote == self.as_remote() && remote == local.as_remote();
+		Output::Now(if is_same { Some(self.count()) } else { None })
+	}
+}
+
+#[test]
+fn sized_receiver() {
+	let queue = EventQueue::new();
+	let small = Remote::new(queue.reference(), Small { count: 0 });
+	let aligned = Remote::new(queue.reference(), Aligned { count: 0 });
+
+	let a = small.send(Identify(small.clone()));
+	let b = aligned.send(Identify(aligned.clone()));
+	let c = small.send(Identify(Remote::new(queue.reference(), Small { count: 0 })));
+
+	queue.reference().close();
+	block_on(queue.process());
+
+	assert!(block_on(a));
+	assert!(block_on(b));
+	assert!(!block_on(c));
+}
+
+#[test]
+fn dyn_receiver() {
+	let queue = EventQueue::new();
+	let small: Remote<dyn Handler<Increment>> = Remote::new(queue.reference(), Small { count: 0 });
+	let aligned: Remote<dyn Handler<Increment>> = Remote::new(queue.reference(), Aligned { count: 0 });
+
+	let a = small.send(Increment(Some(aligned.clone())));
+	let b = aligned.send(Increment(None));
+	let c = aligned.send(Increment(None));
+
+	queue.reference().close();
+	block_on(queue.process());
+
+	assert_eq!(block_on(a), 1);
+	assert_eq!(block_on(b), 1);
+	assert_eq!(block_on(c), 2);
+	assert_eq!(block_on(aligned.ask(Increment(None))), Err(bottle::SendError::QueueClosed));
+}
+
+#[test]
+fn dyn_as_remote() {
+	let queue = EventQueue::new();
+	let aligned = Remote::new(queue.reference(), Aligned { count: 0 });
+	let remote: Remote<dyn Handler<Increment>> = aligned.clone();
+
+	let a = aligned.send(Identify(remote));
+	let b = aligned.send(Increment(None));
+
+	queue.reference().close();
+	block_on(queue.process());
+
+	assert!(block_on(a));
+	assert_eq!(block_on(b), 1);
+	assert_eq!(aligned.as_ptr() as usize % 256, 0);
+}
+
+#[test]
+fn dyn_trait_receiver() {
+	let queue = EventQueue::new();
+	let small: Remote<dyn Counter> = Remote::new(queue.reference(), Small { count: 1 });
+	let aligned: Remote<dyn Counter> = Remote::new(queue.reference(), Aligned { count: 2 });
+
+	let a = small.send(Count(small.clone()));
+	let b = aligned.send(Count(aligned.clone()));
+	let c = aligned.send(Count(small.clone()));
+
+	queue.reference().close();
+	block_on(queue.process());
+
+	assert_eq!(block_on(a), Some(1));
+	assert_eq!(block_on(b), Some(2));
+	assert_eq!(block_on(c), None);
+	assert_eq!(aligned.as_ptr() as *const u8 as usize % 256, 0);
+}
@@ -0,0 +1,176 @@
+//! Reentrant handlers, which let their actor handle other events while they are suspended.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::mpsc;
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue, SendError};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Record the given value.
+struct Record(u32);
+
+impl bottle::Event for Record {
+	type Response = ();
+}
+
+/// Wait for the gate, reentrantly, then record the given value through the actor itself.
+struct Wait(oneshot::Receiver<()>, u32);
+
+impl bottle::Event for Wait {
+	type Response = ();
+}
+
+/// Wait for the gate, reentrantly, then panic.
+struct Panic(oneshot::Receiver<()>);
+
+impl bottle::Event for Panic {
+	type Response = ();
+}
+
+/// Keep the actor busy until the gate is opened.
+struct Busy(oneshot::Receiver<()>);
+
+impl bottle::Event for Busy {
+	type Response = ();
+}
+
+struct Actor {
+	log: mpsc::Sender<u32>
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Record> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Record(value): Record) -> Output<'a, ()> {
+		self.log.send(value).unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Record> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Record(value): Record) -> Output<'a, ()> {
+		self.log.send(value).unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Wait> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Wait(gate, value): Wait) -> Output<'a, ()> {
+		let this: Remote<Actor> = self.as_remote();
+		Output::reentrant(async move {
+			let _ = gate.await;
+			this.send(Record(value)).await;
+		})
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Wait> for Actor {
+	fn handle<'a>(&'a mut self, ctx: &'a mut Context<Self>, Wait(gate, value): Wait) -> Output<'a, ()> {
+		let this: Remote<Actor> = ctx.as_remote();
+		Output::reentrant(async move {
+			let _ = gate.await;
+			this.send(Record(value)).await;
+		})
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Panic> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Panic(gate): Panic) -> Output<'a, ()> {
+		Output::reentrant(async move {
+			let _ = gate.await;
+			panic!("reentrant handler");
+		})
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Panic> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Panic(gate): Panic) -> Output<'a, ()> {
+		Output::reentrant(async move {
+			let _ = gate.await;
+			panic!("reentrant handler");
+		})
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Busy> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Busy(gate): Busy) -> Output<'a, ()> {
+		async move {
+			let _ = gate.await;
+		}.into()
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Busy> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Busy(gate): Busy) -> Output<'a, ()> {
+		async move {
+			let _ = gate.await;
+		}.into()
+	}
+}
+
+#[test]
+fn interleaving() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let reference = queue.reference();
+	let actor = Remote::new(queue.reference(), Actor { log });
+	let processor = std::thread::spawn(move || queue.run_blocking());
+
+	let (open, gate) = oneshot::channel();
+	let wait = actor.ask(Wait(gate, 2));
+
+	// The actor is not busy while the reentrant future is suspended.
+	block_on(actor.ask(Record(1))).unwrap();
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), [1]);
+
+	// The reentrant future can send events to its own actor.
+	open.send(()).unwrap();
+	assert_eq!(block_on(wait), Ok(()));
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), [2]);
+
+	reference.close();
+	processor.join().unwrap();
+}
+
+#[test]
+fn panic_fails_the_actor_once_idle() {
+	let (log, _records) = mpsc::channel();
+	let (probe_log, _probes) = mpsc::channel();
+	let queue = EventQueue::new();
+	let reference = queue.reference();
+	let actor = Remote::new(queue.reference(), Actor { log });
+	let probe = Remote::new(queue.reference(), Actor { log: probe_log });
+	let processor = std::thread::spawn(move || queue.run_blocking());
+
+	let (open_panic, panic_gate) = oneshot::channel();
+	let (open_busy, busy_gate) = oneshot::channel();
+	let panic = actor.ask(Panic(panic_gate));
+	let busy = actor.ask(Busy(busy_gate));
+
+	// Once the probe is handled, the actor is busy.
+	block_on(probe.send(Record(0)));
+
+	open_panic.send(()).unwrap();
+	assert_eq!(block_on(panic), Err(SendError::HandlerPanicked));
+	assert!(!actor.is_failed());
+
+	open_busy.send(()).unwrap();
+	assert_eq!(block_on(busy), Ok(()));
+	while !actor.is_failed() {
+		std::thread::yield_now();
+	}
+	assert_eq!(block_on(actor.ask(Record(1))), Err(SendError::ActorStopped));
+
+	reference.close();
+	processor.join().unwrap();
+}
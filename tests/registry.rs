@@ -0,0 +1,141 @@
+//! Actor identities, names and lookup by name.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue, Registry, AlreadyRegistered};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+struct Ping;
+
+impl bottle::Event for Ping {
+	type Response = u32;
+}
+
+struct Pong;
+
+impl bottle::Event for Pong {
+	type Response = ();
+}
+
+struct Actor(u32);
+
+#[cfg(feature = "nightly")]
+impl Handler<Ping> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Ping) -> Output<'a, u32> {
+		Output::Now(self.0)
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Ping> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Ping) -> Output<'a, u32> {
+		Output::Now(self.0)
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Pong> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Pong) -> Output<'a, ()> {
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Pong> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Pong) -> Output<'a, ()> {
+		Output::Now(())
+	}
+}
+
+#[test]
+fn identity() {
+	let queue = EventQueue::new();
+	let a = Remote::new(queue.reference(), Actor(0));
+	let b = Remote::new(queue.reference(), Actor(1));
+
+	assert!(a.id() < b.id());
+	assert_eq!(a.clone().id(), a.id());
+	assert_eq!(a.downgrade().upgrade().unwrap().id(), a.id());
+	assert_eq!(a.id().to_string(), format!("#{}", a.id().get()));
+
+	assert_eq!(a.name(), None);
+	a.set_name(Some("a"));
+	assert_eq!(a.name().as_deref(), Some("a"));
+	a.set_name(None::<String>);
+	assert_eq!(a.name(), None);
+}
+
+#[test]
+fn register_and_lookup() {
+	let registry = Registry::new();
+	let queue = EventQueue::new();
+	let a = Remote::new(queue.reference(), Actor(0));
+	let b = Remote::new(queue.reference(), Actor(1));
+
+	assert_eq!(registry.register::<Ping, _>("service", &a), Ok(()));
+	assert_eq!(registry.register::<Ping, _>("service", &a), Ok(()));
+	assert_eq!(registry.register::<Ping, _>("service", &b), Err(AlreadyRegistered));
+
+	// Names are registered per event type.
+	assert_eq!(registry.register::<Pong, _>("service", &b), Ok(()));
+	assert_eq!(registry.names(), ["service"]);
+
+	let ping = registry.lookup::<Ping>("service").unwrap();
+	assert_eq!(ping.id(), a.id());
+	assert_eq!(registry.lookup::<Pong>("service").unwrap().id(), b.id());
+	assert!(registry.lookup::<Ping>("other").is_none());
+
+	let response = ping.send(Ping);
+	queue.reference().close();
+	queue.run_blocking();
+	assert_eq!(block_on(response), 0);
+}
+
+#[test]
+fn unregister() {
+	let registry = Registry::new();
+	let queue = EventQueue::new();
+	let a = Remote::new(queue.reference(), Actor(0));
+	let b = Remote::new(queue.reference(), Actor(1));
+
+	registry.register::<Ping, _>("service", &a).unwrap();
+	assert!(registry.unregister::<Ping>("service"));
+	assert!(!registry.unregister::<Ping>("service"));
+	assert!(registry.lookup::<Ping>("service").is_none());
+	assert!(registry.names().is_empty());
+
+	assert_eq!(registry.register::<Ping, _>("service", &b), Ok(()));
+	assert_eq!(registry.lookup::<Ping>("service").unwrap().id(), b.id());
+}
+
+#[test]
+fn stopped_actors_are_deregistered() {
+	let registry = Registry::new();
+	let queue = EventQueue::new();
+	let a = Remote::new(queue.reference(), Actor(0));
+	let b = Remote::new(queue.reference(), Actor(1));
+	registry.register::<Ping, _>("stopped", &a).unwrap();
+	registry.register::<Ping, _>("dropped", &b).unwrap();
+
+	a.stop();
+	std::mem::drop(b);
+	assert!(registry.lookup::<Ping>("stopped").is_none());
+	assert!(registry.lookup::<Ping>("dropped").is_none());
+	assert!(registry.names().is_empty());
+
+	// The name can be taken by another actor.
+	let c = Remote::new(queue.reference(), Actor(2));
+	assert_eq!(registry.register::<Ping, _>("stopped", &c), Ok(()));
+}
+
+#[test]
+fn global() {
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor(0));
+	Registry::global().register::<Ping, _>("registry::global", &actor).unwrap();
+	assert!(std::ptr::eq(Registry::global(), Registry::global()));
+	assert_eq!(Registry::global().lookup::<Ping>("registry::global").unwrap().id(), actor.id());
+}
@@ -0,0 +1,103 @@
+//! Processing a queue with the built-in blocking executor.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::mpsc;
+use std::time::Duration;
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue, Summary, ThreadLocal};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Add to the actor counter, after waiting for the given duration.
+struct Add(u32, Duration);
+
+impl bottle::Event for Add {
+	type Response = u32;
+}
+
+/// Block the actor's thread on a channel.
+struct Block(mpsc::Sender<()>, mpsc::Receiver<()>);
+
+impl bottle::Event for Block {
+	type Response = ();
+}
+
+struct Counter(u32);
+
+#[cfg(feature = "nightly")]
+impl Handler<Add> for Counter {
+	fn handle<'a>(mut self: Receiver<'a, Self>, Add(value, delay): Add) -> Output<'a, u32> {
+		let sleep = self.queue().sleep(delay);
+		async move {
+			sleep.await;
+			self.0 += value;
+			self.0
+		}.into()
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Add> for Counter {
+	fn handle<'a>(&'a mut self, ctx: &'a mut Context<Self>, Add(value, delay): Add) -> Output<'a, u32> {
+		let sleep = ctx.queue().sleep(delay);
+		async move {
+			sleep.await;
+			self.0 += value;
+			self.0
+		}.into()
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Block> for Counter {
+	fn handle<'a>(self: Receiver<'a, Self>, Block(blocked, gate): Block) -> Output<'a, ()> {
+		blocked.send(()).unwrap();
+		let _ = gate.recv();
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Block> for Counter {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Block(blocked, gate): Block) -> Output<'a, ()> {
+		blocked.send(()).unwrap();
+		let _ = gate.recv();
+		Output::Now(())
+	}
+}
+
+#[test]
+fn runs_until_closed() {
+	let queue = EventQueue::new();
+	let counter = Remote::new(queue.reference(), Counter(0));
+	let a = counter.send(Add(1, Duration::from_millis(10)));
+	let b = counter.send(Add(2, Duration::ZERO));
+
+	queue.reference().close();
+	assert_eq!(queue.run_blocking(), Summary { events: 2 });
+	assert_eq!(block_on(a), 1);
+	assert_eq!(block_on(b), 3);
+}
+
+#[test]
+fn wake_up_while_handler_blocks() {
+	let queue = EventQueue::new();
+	let reference = queue.reference();
+	let counter = Remote::new(queue.reference(), Counter(0));
+	let processor = std::thread::spawn(move || queue.run_blocking());
+
+	// The thread is unparked by the event pushed while the handler is blocked on the channel.
+	let (blocked, is_blocked) = mpsc::channel();
+	let (gate, wait) = mpsc::channel();
+	counter.tell(Block(blocked, wait));
+	is_blocked.recv().unwrap();
+	let response = counter.send(Add(1, Duration::ZERO));
+	std::thread::sleep(Duration::from_millis(10));
+	gate.send(()).unwrap();
+
+	assert_eq!(block_on(response), 1);
+	reference.close();
+	assert_eq!(processor.join().unwrap(), Summary { events: 2 });
+}
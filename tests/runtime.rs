@@ -0,0 +1,125 @@
+//! Placement of actors on the workers of a runtime.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::mpsc;
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, Runtime, Placement, Summary};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Get the name of the actor's thread.
+struct Thread;
+
+impl bottle::Event for Thread {
+	type Response = String;
+}
+
+/// Block the actor's thread until the gate is opened.
+struct Block(mpsc::Sender<()>, mpsc::Receiver<()>);
+
+impl bottle::Event for Block {
+	type Response = ();
+}
+
+struct Actor;
+
+#[cfg(feature = "nightly")]
+impl Handler<Thread> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Thread) -> Output<'a, String> {
+		Output::Now(std::thread::current().name().unwrap().to_string())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Thread> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Thread) -> Output<'a, String> {
+		Output::Now(std::thread::current().name().unwrap().to_string())
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Block> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Block(blocked, gate): Block) -> Output<'a, ()> {
+		blocked.send(()).unwrap();
+		let _ = gate.recv();
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Block> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Block(blocked, gate): Block) -> Output<'a, ()> {
+		blocked.send(()).unwrap();
+		let _ = gate.recv();
+		Output::Now(())
+	}
+}
+
+/// Name of the thread running an actor placed with the given strategy.
+fn placed(runtime: &Runtime, placement: Placement) -> String {
+	let actor = Remote::new(runtime.queue(placement), Actor);
+	block_on(actor.send(Thread))
+}
+
+#[test]
+fn round_robin() {
+	let runtime = Runtime::new(3);
+	let threads: Vec<_> = (0..4).map(|_| placed(&runtime, Placement::RoundRobin)).collect();
+	assert_eq!(threads, ["bottle-worker-0", "bottle-worker-1", "bottle-worker-2", "bottle-worker-0"]);
+}
+
+#[test]
+fn pinned() {
+	let runtime = Runtime::new(3);
+	assert_eq!(placed(&runtime, Placement::Pinned(2)), "bottle-worker-2");
+	assert_eq!(placed(&runtime, Placement::Pinned(2)), "bottle-worker-2");
+	assert_eq!(placed(&runtime, Placement::Pinned(0)), "bottle-worker-0");
+}
+
+#[test]
+#[should_panic(expected = "worker index out of bounds")]
+fn pinned_out_of_bounds() {
+	Runtime::new(2).queue(Placement::Pinned(2));
+}
+
+#[test]
+fn least_loaded() {
+	let runtime = Runtime::new(2);
+	let blocked_actor = Remote::new(runtime.queue(Placement::Pinned(0)), Actor);
+
+	// Block the first worker, and let events wait in its queue.
+	let (blocked, is_blocked) = mpsc::channel();
+	let (gate, wait) = mpsc::channel();
+	blocked_actor.tell(Block(blocked, wait));
+	is_blocked.recv().unwrap();
+	let waiting: Vec<_> = (0..3).map(|_| blocked_actor.send(Thread)).collect();
+
+	assert_eq!(placed(&runtime, Placement::LeastLoaded), "bottle-worker-1");
+
+	gate.send(()).unwrap();
+	for thread in waiting {
+		assert_eq!(block_on(thread), "bottle-worker-0");
+	}
+}
+
+#[test]
+fn shutdown_and_join() {
+	let runtime = Runtime::new(2);
+	assert_eq!(runtime.workers(), 2);
+	let actor = Remote::new(runtime.queue(Placement::Pinned(1)), Actor);
+	let responses: Vec<_> = (0..3).map(|_| actor.send(Thread)).collect();
+
+	runtime.shutdown();
+	assert_eq!(runtime.join(), [Summary { events: 0 }, Summary { events: 3 }]);
+	for thread in responses {
+		assert_eq!(block_on(thread), "bottle-worker-1");
+	}
+}
+
+#[test]
+#[should_panic(expected = "at least one worker")]
+fn no_worker() {
+	Runtime::new(0);
+}
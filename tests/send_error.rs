@@ -0,0 +1,170 @@
+//! Events that cannot be handled resolve into a `SendError` instead of hanging, with `Remote::ask`.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::mpsc;
+use std::thread;
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue, SendError};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+struct Ping;
+
+impl bottle::Event for Ping {
+	type Response = ();
+}
+
+/// Records the thread in which it is dropped.
+struct Probe(mpsc::Sender<thread::ThreadId>);
+
+impl bottle::Event for Probe {
+	type Response = ();
+}
+
+impl Drop for Probe {
+	fn drop(&mut self) {
+		let _ = self.0.send(thread::current().id());
+	}
+}
+
+struct Actor;
+
+#[cfg(feature = "nightly")]
+impl Handler<Ping> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Ping) -> Output<'a, ()> {
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Ping> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Ping) -> Output<'a, ()> {
+		Output::Now(())
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Probe> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Probe) -> Output<'a, ()> {
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Probe> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Probe) -> Output<'a, ()> {
+		Output::Now(())
+	}
+}
+
+#[test]
+fn queue_closed_after_termination() {
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor);
+	let before = actor.ask(Ping);
+
+	queue.reference().close();
+	queue.run_blocking();
+
+	assert_eq!(block_on(before), Ok(()));
+	assert_eq!(block_on(actor.ask(Ping)), Err(SendError::QueueClosed));
+	assert_eq!(actor.try_send(Ping).err(), Some(SendError::QueueClosed));
+}
+
+#[test]
+fn queue_closed_when_processor_is_dropped() {
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor);
+	let pending = actor.ask(Ping);
+
+	std::mem::drop(queue.process());
+	assert_eq!(block_on(pending), Err(SendError::QueueClosed));
+	assert_eq!(block_on(actor.ask(Ping)), Err(SendError::QueueClosed));
+}
+
+#[test]
+fn queue_closed_when_queue_is_dropped() {
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor);
+	let pending = actor.ask(Ping);
+
+	std::mem::drop(queue);
+	assert_eq!(block_on(pending), Err(SendError::QueueClosed));
+	assert_eq!(block_on(actor.ask(Ping)), Err(SendError::QueueClosed));
+}
+
+#[test]
+fn events_are_discarded_in_the_queue_thread() {
+	let (dropped, drops) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor);
+	let pending = actor.ask(Probe(dropped));
+
+	let owner = thread::spawn(move || {
+		std::mem::drop(queue);
+		thread::current().id()
+	}).join().unwrap();
+
+	assert_eq!(block_on(pending), Err(SendError::QueueClosed));
+	assert_eq!(drops.try_iter().collect::<Vec<_>>(), [owner]);
+}
+
+#[test]
+fn actor_stopped() {
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor);
+	let before = actor.ask(Ping);
+	actor.stop();
+
+	assert_eq!(block_on(actor.ask(Ping)), Err(SendError::ActorStopped));
+	assert_eq!(actor.try_send(Ping).err(), Some(SendError::ActorStopped));
+
+	queue.reference().close();
+	queue.run_blocking();
+	assert_eq!(block_on(before), Ok(()));
+}
+
+#[test]
+fn events_sent_during_termination_resolve() {
+	for _ in 0..20 {
+		let queue = EventQueue::new();
+		let reference = queue.reference();
+		let actor = Remote::new(queue.reference(), Actor);
+		let processor = thread::spawn(move || queue.run_blocking());
+
+		let sender = thread::spawn(move || {
+			(0..1000).map(|_| actor.ask(Ping)).collect::<Vec<_>>()
+		});
+
+		reference.close();
+		processor.join().unwrap();
+		for response in sender.join().unwrap() {
+			assert!(matches!(block_on(response), Ok(()) | Err(SendError::QueueClosed)));
+		}
+	}
+}
+
+#[test]
+#[should_panic(expected = "event could not be handled: actor stopped")]
+fn send_panics() {
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor);
+	actor.stop();
+	block_on(actor.send(Ping));
+}
+
+#[test]
+fn weak_ask() {
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor);
+	let weak = actor.downgrade();
+	let response = weak.ask(Ping).unwrap();
+
+	actor.stop();
+	assert!(weak.ask(Ping).is_none());
+	queue.reference().close();
+	queue.run_blocking();
+	assert_eq!(block_on(response), Ok(()));
+}
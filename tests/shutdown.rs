@@ -0,0 +1,139 @@
+//! Shutting down an event queue processor.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::mpsc;
+use std::time::Duration;
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue, Summary, SendError, ThreadLocal};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Record the given value.
+struct Record(u32);
+
+impl bottle::Event for Record {
+	type Response = ();
+}
+
+/// Wait for the gate, then record the given value.
+struct Wait(oneshot::Receiver<()>, u32);
+
+impl bottle::Event for Wait {
+	type Response = ();
+}
+
+/// Close the actor's queue.
+struct Close;
+
+impl bottle::Event for Close {
+	type Response = ();
+}
+
+struct Actor {
+	log: mpsc::Sender<u32>
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Record> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Record(value): Record) -> Output<'a, ()> {
+		self.log.send(value).unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Record> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Record(value): Record) -> Output<'a, ()> {
+		self.log.send(value).unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Wait> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Wait(gate, value): Wait) -> Output<'a, ()> {
+		async move {
+			let _ = gate.await;
+			self.log.send(value).unwrap();
+		}.into()
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Wait> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Wait(gate, value): Wait) -> Output<'a, ()> {
+		async move {
+			let _ = gate.await;
+			self.log.send(value).unwrap();
+		}.into()
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Close> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Close) -> Output<'a, ()> {
+		self.queue().close();
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Close> for Actor {
+	fn handle<'a>(&'a mut self, ctx: &'a mut Context<Self>, _event: Close) -> Output<'a, ()> {
+		ctx.queue().close();
+		Output::Now(())
+	}
+}
+
+#[test]
+fn waits_for_pending_handlers() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor { log });
+
+	let (open, gate) = oneshot::channel();
+	let wait = actor.ask(Wait(gate, 1));
+	let record = actor.ask(Record(2));
+	queue.reference().close();
+
+	let opener = std::thread::spawn(move || {
+		std::thread::sleep(Duration::from_millis(10));
+		open.send(()).unwrap();
+	});
+
+	assert_eq!(block_on(queue.process()), Summary { events: 2 });
+	opener.join().unwrap();
+	assert_eq!(block_on(wait), Ok(()));
+	assert_eq!(block_on(record), Ok(()));
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), [1, 2]);
+}
+
+#[test]
+fn close_from_handler() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor { log });
+
+	let responses = vec![actor.ask(Record(1)), actor.ask(Close), actor.ask(Record(2))];
+	assert_eq!(block_on(queue.process()), Summary { events: 3 });
+	assert_eq!(responses.into_iter().map(block_on).collect::<Vec<_>>(), [Ok(()), Ok(()), Ok(())]);
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), [1, 2]);
+
+	assert_eq!(block_on(actor.ask(Record(3))), Err(SendError::QueueClosed));
+	assert!(records.try_iter().next().is_none());
+}
+
+#[test]
+fn pending_timers_are_cancelled() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor { log });
+
+	let _timer = actor.send_after(Duration::from_secs(3600), Record(1));
+	queue.reference().close();
+	assert_eq!(block_on(queue.process()), Summary { events: 0 });
+	assert!(records.try_iter().next().is_none());
+}
@@ -0,0 +1,223 @@
+//! Restart strategies and intensity of supervisors.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use futures::FutureExt;
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue, EventQueueRef, EventQueueProcessor, Lifecycle, SendError, ActorId};
+use bottle::{Capacity, Overflow, ManualClock};
+use bottle::{Supervisor, Strategy, Intensity, ChildId, GetChild};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Make the worker panic.
+struct Crash;
+
+impl bottle::Event for Crash {
+	type Response = ();
+}
+
+struct Worker;
+
+impl Lifecycle for Worker {}
+
+#[cfg(feature = "nightly")]
+impl Handler<Crash> for Worker {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Crash) -> Output<'a, ()> {
+		panic!("crash")
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Crash> for Worker {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Crash) -> Output<'a, ()> {
+		panic!("crash")
+	}
+}
+
+/// Wait until `condition` holds, or panic after a while.
+fn wait_until(mut condition: impl FnMut() -> bool) {
+	let start = Instant::now();
+	while !condition() {
+		assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+		std::thread::sleep(Duration::from_millis(1))
+	}
+}
+
+struct Fixture {
+	queue: EventQueueRef,
+	processor: std::thread::JoinHandle<()>,
+	supervisor: Remote<Supervisor>,
+	children: Vec<ChildId<Worker>>
+}
+
+impl Fixture {
+	fn new(strategy: Strategy, intensity: Intensity) -> Fixture {
+		let queue = EventQueue::new();
+		let reference = queue.reference();
+		let mut supervisor = Supervisor::new(strategy, intensity);
+		let children = (0..3).map(|_| supervisor.add(reference.clone(), || Worker)).collect();
+		let supervisor = Remote::spawn(reference.clone(), supervisor);
+		let processor = std::thread::spawn(move || {
+			queue.run_blocking();
+		});
+
+		Fixture {
+			queue: reference,
+			processor,
+			supervisor,
+			children
+		}
+	}
+
+	fn child(&self, index: usize) -> Option<Remote<Worker>> {
+		block_on(self.supervisor.ask(GetChild(self.children[index]))).unwrap_or(None)
+	}
+
+	fn ids(&self) -> Vec<ActorId> {
+		(0..self.children.len()).map(|index| self.child(index).unwrap().id()).collect()
+	}
+
+	/// Crash the given child, and wait until it has been restarted.
+	///
+	/// Returns the identifiers of the children before and after the restart.
+	fn crash(&self, index: usize) -> (Vec<ActorId>, Vec<ActorId>) {
+		let before = self.ids();
+		let child = self.child(index).unwrap();
+		assert_eq!(block_on(child.ask(Crash)), Err(SendError::HandlerPanicked));
+		wait_until(|| self.child(index).is_some_and(|child| child.id() != before[index]));
+		(before, self.ids())
+	}
+
+	fn close(self) {
+		self.supervisor.stop();
+		self.queue.close();
+		self.processor.join().unwrap()
+	}
+}
+
+#[test]
+fn one_for_one() {
+	let fixture = Fixture::new(Strategy::OneForOne, Intensity::default());
+	let (before, after) = fixture.crash(1);
+	assert_eq!(before[0], after[0]);
+	assert_ne!(before[1], after[1]);
+	assert_eq!(before[2], after[2]);
+	fixture.close()
+}
+
+#[test]
+fn one_for_all() {
+	let fixture = Fixture::new(Strategy::OneForAll, Intensity::default());
+	let (before, after) = fixture.crash(1);
+	assert!(before.iter().zip(&after).all(|(a, b)| a != b));
+	fixture.close()
+}
+
+#[test]
+fn rest_for_one() {
+	let fixture = Fixture::new(Strategy::RestForOne, Intensity::default());
+	let (before, after) = fixture.crash(1);
+	assert_eq!(before[0], after[0]);
+	assert_ne!(before[1], after[1]);
+	assert_ne!(before[2], after[2]);
+	fixture.close()
+}
+
+#[test]
+fn intensity_exceeded() {
+	let fixture = Fixture::new(Strategy::OneForOne, Intensity::new(1, Duration::from_secs(60)));
+	fixture.crash(0);
+
+	let children: Vec<_> = (0..3).map(|index| fixture.child(index).unwrap()).collect();
+	assert_eq!(block_on(children[0].ask(Crash)), Err(SendError::HandlerPanicked));
+	wait_until(|| fixture.supervisor.is_failed());
+
+	assert!(children.iter().all(Remote::is_stopped));
+	assert_eq!(block_on(fixture.supervisor.ask(GetChild(fixture.children[0]))), Err(SendError::ActorStopped));
+	fixture.close()
+}
+
+/// A supervisor and its only child in a queue processed by hand, in virtual time.
+struct Manual {
+	clock: Arc<ManualClock>,
+	processor: EventQueueProcessor,
+	supervisor: Remote<Supervisor>,
+	child: ChildId<Worker>
+}
+
+impl Manual {
+	fn new(intensity: Intensity) -> Manual {
+		let clock = Arc::new(ManualClock::new());
+		let queue = EventQueue::new();
+		queue.reference().set_clock(clock.clone());
+		let mut supervisor = Supervisor::new(Strategy::OneForOne, intensity);
+		let child = supervisor.add(queue.reference(), || Worker);
+		let supervisor = Remote::spawn(queue.reference(), supervisor);
+
+		let mut manual = Manual {
+			clock,
+			processor: queue.process(),
+			supervisor,
+			child
+		};
+
+		manual.process();
+		manual
+	}
+
+	fn process(&mut self) {
+		for _ in 0..2 {
+			assert!((&mut self.processor).now_or_never().is_none());
+		}
+	}
+
+	fn child(&mut self) -> Option<Remote<Worker>> {
+		let child = self.supervisor.ask(GetChild(self.child));
+		self.process();
+		block_on(child).unwrap_or(None)
+	}
+
+	/// Crash the child, and return whether it has been restarted.
+	fn crash(&mut self) -> bool {
+		let before = self.child().unwrap();
+		let crash = before.ask(Crash);
+		self.process();
+		assert_eq!(block_on(crash), Err(SendError::HandlerPanicked));
+		self.child().is_some_and(|after| after.id() != before.id())
+	}
+}
+
+#[test]
+fn failures_bypass_the_capacity() {
+	let mut manual = Manual::new(Intensity::default());
+	manual.supervisor.set_capacity(Some(Capacity::new(1, Overflow::DropNewest)));
+	let before = manual.child().unwrap();
+
+	// The supervisor mailbox is full when the failure is reported.
+	let crash = before.ask(Crash);
+	let waiting = manual.supervisor.ask(GetChild(manual.child));
+	assert_eq!(manual.supervisor.try_send(GetChild(manual.child)).err(), Some(SendError::Full));
+	manual.process();
+
+	assert_eq!(block_on(crash), Err(SendError::HandlerPanicked));
+	assert!(block_on(waiting).is_ok());
+	assert_ne!(manual.child().unwrap().id(), before.id());
+}
+
+#[test]
+fn intensity_period() {
+	let mut manual = Manual::new(Intensity::new(1, Duration::from_secs(10)));
+	assert!(manual.crash());
+
+	// The restart is forgotten once the period has elapsed.
+	manual.clock.advance(Duration::from_secs(11));
+	assert!(manual.crash());
+
+	manual.clock.advance(Duration::from_secs(9));
+	assert!(!manual.crash());
+	assert!(manual.supervisor.is_failed());
+}
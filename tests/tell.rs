@@ -0,0 +1,152 @@
+//! Fire-and-forget events.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::mpsc;
+use futures::channel::oneshot;
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue, Capacity, Overflow};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Record the given value, synchronously.
+struct Now(u32);
+
+impl bottle::Event for Now {
+	type Response = ();
+}
+
+/// Record the given value, asynchronously.
+struct Later(u32);
+
+impl bottle::Event for Later {
+	type Response = ();
+}
+
+/// Keep the actor busy until the gate is opened.
+struct Busy(oneshot::Receiver<()>);
+
+impl bottle::Event for Busy {
+	type Response = ();
+}
+
+struct Actor {
+	log: mpsc::Sender<u32>
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Now> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Now(value): Now) -> Output<'a, ()> {
+		self.log.send(value).unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Now> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Now(value): Now) -> Output<'a, ()> {
+		self.log.send(value).unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Later> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Later(value): Later) -> Output<'a, ()> {
+		async move {
+			futures::future::ready(()).await;
+			self.log.send(value).unwrap();
+		}.into()
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Later> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Later(value): Later) -> Output<'a, ()> {
+		async move {
+			futures::future::ready(()).await;
+			self.log.send(value).unwrap();
+		}.into()
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Busy> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Busy(gate): Busy) -> Output<'a, ()> {
+		async move {
+			let _ = gate.await;
+		}.into()
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Busy> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Busy(gate): Busy) -> Output<'a, ()> {
+		async move {
+			let _ = gate.await;
+		}.into()
+	}
+}
+
+#[test]
+fn handled_in_order() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor { log });
+
+	actor.tell(Now(1));
+	actor.tell(Later(2));
+	let response = actor.send(Now(3));
+	actor.tell(Later(4));
+
+	queue.reference().close();
+	queue.run_blocking();
+	block_on(response);
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), [1, 2, 3, 4]);
+}
+
+#[test]
+fn handed_over_by_a_busy_actor() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let reference = queue.reference();
+	let actor = Remote::new(queue.reference(), Actor { log });
+	let processor = std::thread::spawn(move || queue.run_blocking());
+
+	let (open, gate) = oneshot::channel();
+	let busy = actor.send(Busy(gate));
+	actor.tell(Later(1));
+	actor.tell(Now(2));
+	actor.tell(Later(3));
+	open.send(()).unwrap();
+
+	block_on(busy);
+	block_on(actor.send(Now(4)));
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), [1, 2, 3, 4]);
+
+	reference.close();
+	processor.join().unwrap();
+}
+
+#[test]
+fn dropped_silently() {
+	let (log, records) = mpsc::channel();
+	let queue = EventQueue::new();
+	let full = Remote::new(queue.reference(), Actor { log: log.clone() });
+	full.set_capacity(Some(Capacity::new(1, Overflow::Wait)));
+	let stopped = Remote::new(queue.reference(), Actor { log });
+	stopped.stop();
+
+	full.tell(Now(1));
+	full.tell(Now(2));
+	stopped.tell(Now(3));
+
+	queue.reference().close();
+	queue.run_blocking();
+	assert_eq!(records.try_iter().collect::<Vec<_>>(), [1]);
+
+	// The processor has terminated.
+	full.tell(Now(4));
+	assert!(records.try_iter().next().is_none());
+}
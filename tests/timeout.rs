@@ -0,0 +1,196 @@
+//! Timeouts elapse even if the receiver's thread is stalled.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::{mpsc, Arc, Mutex};
+use std::collections::HashSet;
+use std::task::Waker;
+use std::time::{Duration, Instant};
+use futures::FutureExt;
+use futures::executor::block_on;
+use bottle::{Output, Remote, Handler, EventQueue, EventQueueRef, Clock, ManualClock, SendError};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Block the actor's thread until the gate is opened.
+struct Stall(mpsc::Sender<()>, mpsc::Receiver<()>);
+
+impl bottle::Event for Stall {
+	type Response = ();
+}
+
+struct Ping;
+
+impl bottle::Event for Ping {
+	type Response = ();
+}
+
+struct Actor;
+
+#[cfg(feature = "nightly")]
+impl Handler<Stall> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Stall(stalled, gate): Stall) -> Output<'a, ()> {
+		stalled.send(()).unwrap();
+		let _ = gate.recv();
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Stall> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Stall(stalled, gate): Stall) -> Output<'a, ()> {
+		stalled.send(()).unwrap();
+		let _ = gate.recv();
+		Output::Now(())
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Ping> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, _event: Ping) -> Output<'a, ()> {
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Ping> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, _event: Ping) -> Output<'a, ()> {
+		Output::Now(())
+	}
+}
+
+/// An actor whose thread is stalled until it is resumed.
+struct Stalled {
+	actor: Remote<Actor>,
+	queue: EventQueueRef,
+	gate: mpsc::Sender<()>,
+	processor: std::thread::JoinHandle<()>
+}
+
+impl Stalled {
+	fn new(clock: Option<Arc<ManualClock>>) -> Stalled {
+		let queue = EventQueue::new();
+		if let Some(clock) = clock {
+			queue.reference().set_clock(clock);
+		}
+
+		let actor = Remote::new(queue.reference(), Actor);
+		let reference = queue.reference();
+		let processor = std::thread::spawn(move || {
+			queue.run_blocking();
+		});
+
+		let (stalled, is_stalled) = mpsc::channel();
+		let (gate, wait) = mpsc::channel();
+		actor.tell(Stall(stalled, wait));
+		is_stalled.recv().unwrap();
+
+		Stalled {
+			actor,
+			queue: reference,
+			gate,
+			processor
+		}
+	}
+
+	fn resume(self) {
+		self.gate.send(()).unwrap();
+		self.queue.close();
+		self.processor.join().unwrap()
+	}
+}
+
+#[test]
+fn timeout_elapses_while_stalled() {
+	let stalled = Stalled::new(None);
+	let response = stalled.actor.ask(Ping).timeout(Duration::from_millis(20));
+	assert_eq!(block_on(response), Err(SendError::Timeout));
+	stalled.resume()
+}
+
+#[test]
+fn reply_timeout_elapses_while_stalled() {
+	let stalled = Stalled::new(None);
+	stalled.actor.set_reply_timeout(Some(Duration::from_millis(20)));
+	assert_eq!(block_on(stalled.actor.ask(Ping)), Err(SendError::Timeout));
+	stalled.resume()
+}
+
+#[test]
+fn timeout_with_manual_clock() {
+	let clock = Arc::new(ManualClock::new());
+	let stalled = Stalled::new(Some(clock.clone()));
+	let mut response = stalled.actor.ask(Ping).timeout(Duration::from_secs(10));
+
+	assert_eq!((&mut response).now_or_never(), None);
+	clock.advance(Duration::from_secs(9));
+	assert_eq!((&mut response).now_or_never(), None);
+	clock.advance(Duration::from_secs(1));
+	assert_eq!(block_on(response), Err(SendError::Timeout));
+	stalled.resume()
+}
+
+#[test]
+fn response_in_time() {
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor);
+	let response = actor.send(Ping).timeout(Duration::from_secs(60));
+
+	queue.reference().close();
+	queue.run_blocking();
+	assert_eq!(block_on(response), Ok(()));
+}
+
+#[test]
+fn failure_in_time() {
+	let queue = EventQueue::new();
+	let actor = Remote::new(queue.reference(), Actor);
+	actor.stop();
+
+	let response = actor.send(Ping).timeout(Duration::from_secs(60));
+	assert_eq!(block_on(response), Err(SendError::ActorStopped));
+}
+
+/// A manual clock keeping track of its pending wake-ups.
+#[derive(Default)]
+struct TrackingClock {
+	clock: ManualClock,
+	pending: Mutex<HashSet<u64>>
+}
+
+impl Clock for TrackingClock {
+	fn now(&self) -> Instant {
+		self.clock.now()
+	}
+
+	fn wake_at(&self, deadline: Instant, waker: Waker) -> u64 {
+		let key = self.clock.wake_at(deadline, waker);
+		self.pending.lock().unwrap().insert(key);
+		key
+	}
+
+	fn cancel(&self, key: u64) {
+		self.pending.lock().unwrap().remove(&key);
+		self.clock.cancel(key)
+	}
+}
+
+#[test]
+fn dropped_timeouts_release_their_wakeup() {
+	let clock = Arc::new(TrackingClock::default());
+	let queue = EventQueue::new();
+	queue.reference().set_clock(clock.clone());
+	let actor = Remote::new(queue.reference(), Actor);
+	actor.set_reply_timeout(Some(Duration::from_secs(60)));
+
+	let mut responses: Vec<_> = (0..3).map(|_| actor.ask(Ping).timeout(Duration::from_secs(60))).collect();
+	for response in &mut responses {
+		assert_eq!(response.now_or_never(), None);
+	}
+
+	// Both the reply timeout and the timeout of each response are armed.
+	assert_eq!(clock.pending.lock().unwrap().len(), 6);
+	std::mem::drop(responses);
+	assert!(clock.pending.lock().unwrap().is_empty());
+}
@@ -0,0 +1,185 @@
+//! Delayed sends, intervals and sleeps, in virtual time.
+#![cfg_attr(feature = "nightly", feature(arbitrary_self_types))]
+
+use std::sync::{mpsc, Arc};
+use std::time::Duration;
+use futures::FutureExt;
+use bottle::{Output, Remote, Handler, EventQueue, EventQueueProcessor, ManualClock, ThreadLocal};
+#[cfg(feature = "nightly")]
+use bottle::Receiver;
+#[cfg(not(feature = "nightly"))]
+use bottle::Context;
+
+/// Record the given value.
+struct Record(u32);
+
+impl bottle::Event for Record {
+	type Response = ();
+}
+
+/// Record the given value after sleeping for the given duration.
+struct Sleep(u32, Duration);
+
+impl bottle::Event for Sleep {
+	type Response = ();
+}
+
+struct Actor {
+	log: mpsc::Sender<u32>
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Record> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Record(value): Record) -> Output<'a, ()> {
+		self.log.send(value).unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Record> for Actor {
+	fn handle<'a>(&'a mut self, _ctx: &'a mut Context<Self>, Record(value): Record) -> Output<'a, ()> {
+		self.log.send(value).unwrap();
+		Output::Now(())
+	}
+}
+
+#[cfg(feature = "nightly")]
+impl Handler<Sleep> for Actor {
+	fn handle<'a>(self: Receiver<'a, Self>, Sleep(value, duration): Sleep) -> Output<'a, ()> {
+		let sleep = self.queue().sleep(duration);
+		async move {
+			sleep.await;
+			self.log.send(value).unwrap();
+		}.into()
+	}
+}
+
+#[cfg(not(feature = "nightly"))]
+impl Handler<Sleep> for Actor {
+	fn handle<'a>(&'a mut self, ctx: &'a mut Context<Self>, Sleep(value, duration): Sleep) -> Output<'a, ()> {
+		let sleep = ctx.queue().sleep(duration);
+		async move {
+			sleep.await;
+			self.log.send(value).unwrap();
+		}.into()
+	}
+}
+
+/// An actor whose queue is processed by hand, in virtual time.
+struct Fixture {
+	clock: Arc<ManualClock>,
+	actor: Remote<Actor>,
+	processor: EventQueueProcessor,
+	records: mpsc::Receiver<u32>
+}
+
+impl Fixture {
+	fn new() -> Fixture {
+		let clock = Arc::new(ManualClock::new());
+		let queue = EventQueue::new();
+		queue.reference().set_clock(clock.clone());
+		let (log, records) = mpsc::channel();
+		let actor = Remote::new(queue.reference(), Actor { log });
+
+		Fixture {
+			clock,
+			actor,
+			processor: queue.process(),
+			records
+		}
+	}
+
+	/// Advance the time, process the queue, and return the recorded values.
+	fn advance(&mut self, millis: u64) -> Vec<u32> {
+		self.clock.advance(Duration::from_millis(millis));
+
+		// Futures woken up by the timers are polled in the next round.
+		for _ in 0..2 {
+			assert!((&mut self.processor).now_or_never().is_none());
+		}
+
+		self.records.try_iter().collect()
+	}
+}
+
+#[test]
+fn send_after() {
+	let mut fixture = Fixture::new();
+	let _short = fixture.actor.send_after(Duration::from_millis(10), Record(1));
+	let _long = fixture.actor.send_after(Duration::from_secs(1), Record(2));
+
+	assert_eq!(fixture.advance(0), []);
+	assert_eq!(fixture.advance(9), []);
+	assert_eq!(fixture.advance(1), [1]);
+
+	// Beyond a turn of the timer wheel.
+	assert_eq!(fixture.advance(500), []);
+	assert_eq!(fixture.advance(490), [2]);
+	assert_eq!(fixture.advance(1000), []);
+}
+
+#[test]
+fn send_after_in_order() {
+	let mut fixture = Fixture::new();
+	let _timers: Vec<_> = [(3, 20), (1, 10), (2, 20)].iter().map(|&(value, delay)| {
+		fixture.actor.send_after(Duration::from_millis(delay), Record(value))
+	}).collect();
+
+	assert_eq!(fixture.advance(50), [1, 3, 2]);
+}
+
+#[test]
+fn cancel() {
+	let mut fixture = Fixture::new();
+	let cancelled = fixture.actor.send_after(Duration::from_millis(10), Record(1));
+	let _kept = fixture.actor.send_after(Duration::from_millis(10), Record(2));
+	cancelled.cancel();
+
+	assert_eq!(fixture.advance(10), [2]);
+
+	// Cancelling a fired timer has no effect.
+	cancelled.cancel();
+	assert_eq!(fixture.advance(10), []);
+}
+
+#[test]
+fn send_interval() {
+	let mut fixture = Fixture::new();
+	let mut count = 0;
+	let interval = fixture.actor.send_interval(Duration::from_millis(10), move || {
+		count += 1;
+		Record(count)
+	});
+
+	assert_eq!(fixture.advance(5), []);
+	assert_eq!(fixture.advance(5), [1]);
+	assert_eq!(fixture.advance(10), [2]);
+	assert_eq!(fixture.advance(10), [3]);
+
+	interval.cancel();
+	assert_eq!(fixture.advance(50), []);
+}
+
+#[test]
+fn send_interval_until_stopped() {
+	let mut fixture = Fixture::new();
+	let _interval = fixture.actor.send_interval(Duration::from_millis(10), || Record(0));
+	assert_eq!(fixture.advance(10), [0]);
+
+	fixture.actor.stop();
+	assert_eq!(fixture.advance(10), []);
+	assert_eq!(fixture.advance(10), []);
+}
+
+#[test]
+fn sleep() {
+	let mut fixture = Fixture::new();
+	fixture.actor.tell(Sleep(1, Duration::from_millis(20)));
+	fixture.actor.tell(Record(2));
+
+	// The actor is busy while its handler sleeps.
+	assert_eq!(fixture.advance(0), []);
+	assert_eq!(fixture.advance(10), []);
+	assert_eq!(fixture.advance(10), [1, 2]);
+}